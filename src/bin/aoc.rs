@@ -0,0 +1,317 @@
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, ExitStatus};
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+
+const REPL_DAYS: [u8; 4] = [6, 9, 12, 16];
+const REPORT_DAYS: [u8; 5] = [5, 9, 12, 13, 20];
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const RUN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Extra time a solver gets past its per part budgets before the runner kills it, for solvers
+/// that don't check their deadline (or only check it between expensive steps)
+const KILL_GRACE: Duration = Duration::from_secs(5);
+const PARTS_PER_DAY: u32 = 2;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("repl") => {
+            let day = parse_day(args.get(1));
+
+            if !REPL_DAYS.contains(&day) {
+                eprintln!("Day {} has no repl, try one of {:?}", day, REPL_DAYS);
+                process::exit(1);
+            }
+
+            let status = day_command(day)
+                .arg("repl")
+                .args(args.iter().skip(2))
+                .status()
+                .unwrap();
+
+            process::exit(status.code().unwrap_or(1));
+        }
+        Some("report") => {
+            let day = parse_day(args.get(1));
+
+            if !REPORT_DAYS.contains(&day) {
+                eprintln!("Day {} has no report, try one of {:?}", day, REPORT_DAYS);
+                process::exit(1);
+            }
+
+            let path = args
+                .get(2)
+                .cloned()
+                .unwrap_or_else(|| format!("Report_Day_{}.md", day));
+
+            let status = day_command(day).arg("report").arg(&path).status().unwrap();
+
+            if status.success() {
+                println!("Wrote {}", path);
+            }
+
+            process::exit(status.code().unwrap_or(1));
+        }
+        Some("run") => {
+            let mut timeout = None;
+            let mut days = vec![];
+
+            let mut run_args = args.iter().skip(1);
+            while let Some(arg) = run_args.next() {
+                if arg == "--timeout" {
+                    match run_args.next().map(|secs| secs.parse::<f64>()) {
+                        Some(Ok(secs)) if secs >= 0.0 => {
+                            timeout = Some(Duration::from_secs_f64(secs))
+                        }
+                        _ => {
+                            print_usage();
+                            process::exit(1);
+                        }
+                    }
+                } else {
+                    days.push(parse_day(Some(arg)));
+                }
+            }
+
+            if days.is_empty() {
+                print_usage();
+                process::exit(1);
+            }
+
+            run(&days, timeout);
+        }
+        Some("watch") => {
+            let day = parse_day(args.get(1));
+
+            // Files to watch come before `--`, arguments for the day after it
+            let rest = args.get(3..).unwrap_or_default();
+            let (extra_files, day_args) = match rest.iter().position(|arg| arg == "--") {
+                Some(split) => (&rest[..split], &rest[split + 1..]),
+                None => (rest, &[][..]),
+            };
+
+            match args.get(2) {
+                Some(input) => watch(day, input, extra_files, day_args),
+                None => {
+                    print_usage();
+                    process::exit(1);
+                }
+            }
+        }
+        _ => {
+            print_usage();
+            process::exit(1);
+        }
+    }
+}
+
+fn print_usage() {
+    eprintln!("Usage:");
+    eprintln!("    aoc repl <day> [input]");
+    eprintln!("    aoc report <day> [output]");
+    eprintln!("    aoc run [--timeout <seconds per part>] <day>...");
+    eprintln!("    aoc watch <day> <input> [extra files...] [-- <day arguments>...]");
+}
+
+fn parse_day(arg: Option<&String>) -> u8 {
+    match arg.map(|day| day.trim_start_matches("day_").parse::<u8>()) {
+        Some(Ok(day)) => day,
+        _ => {
+            print_usage();
+            process::exit(1);
+        }
+    }
+}
+
+/// Builds a `cargo run` invocation of the given day's binary, ready for extra arguments
+fn day_command(day: u8) -> Command {
+    let mut command = cargo_command("run", day);
+    command.arg("--");
+    command
+}
+
+fn cargo_command(subcommand: &str, day: u8) -> Command {
+    let mut command = Command::new("cargo");
+
+    command
+        .arg(subcommand)
+        .arg("--quiet")
+        .arg("--release")
+        .arg("--manifest-path")
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+        .arg("--bin")
+        .arg(format!("day_{}", day));
+
+    command
+}
+
+enum RunOutcome {
+    Finished(ExitStatus, Duration),
+    Killed(Duration),
+    BuildFailed,
+}
+
+/// Solves each day in turn, passing the per part budget down to the solvers through
+/// `AOC_PART_TIMEOUT_MS` and killing any day that overruns all of its parts
+fn run(days: &[u8], timeout: Option<Duration>) {
+    let mut outcomes = vec![];
+
+    for &day in days {
+        println!("=== day_{} ===", day);
+
+        // Build first so compile times don't eat into the budget
+        if !cargo_command("build", day).status().unwrap().success() {
+            outcomes.push((day, RunOutcome::BuildFailed));
+            continue;
+        }
+
+        let mut command = day_command(day);
+        if let Some(timeout) = timeout {
+            command.env("AOC_PART_TIMEOUT_MS", timeout.as_millis().to_string());
+        }
+
+        let start = Instant::now();
+        let mut child = command.spawn().unwrap();
+
+        let outcome = loop {
+            if let Some(status) = child.try_wait().unwrap() {
+                break RunOutcome::Finished(status, start.elapsed());
+            }
+
+            if let Some(timeout) = timeout {
+                if start.elapsed() > timeout * PARTS_PER_DAY + KILL_GRACE {
+                    child.kill().unwrap();
+                    child.wait().unwrap();
+                    break RunOutcome::Killed(start.elapsed());
+                }
+            }
+
+            thread::sleep(RUN_POLL_INTERVAL);
+        };
+
+        outcomes.push((day, outcome));
+    }
+
+    println!("=== Summary ===");
+    for (day, outcome) in outcomes {
+        match outcome {
+            RunOutcome::Finished(status, elapsed) if status.success() => {
+                println!("day_{}: finished in {:?}", day, elapsed)
+            }
+            RunOutcome::Finished(status, elapsed) => {
+                println!("day_{}: failed with {} after {:?}", day, status, elapsed)
+            }
+            RunOutcome::Killed(elapsed) => println!("day_{}: killed after {:?}", day, elapsed),
+            RunOutcome::BuildFailed => println!("day_{}: build failed", day),
+        }
+    }
+}
+
+/// Re-solves a day every time its input or one of the extra files changes, printing which
+/// answer lines differ from the previous run. The day arguments (e.g. day_2's `--max-step 4`)
+/// are passed to every run, so a variant of the puzzle can be watched.
+///
+/// Each run happens in `target/aoc_watch/day_N` with the input copied to `Input_Day_N`
+/// and the extra files copied under their own names, so the solvers don't need to change.
+/// While a watched file is missing, nothing runs until it's back
+fn watch(day: u8, input: &str, extra_files: &[String], day_args: &[String]) -> ! {
+    let run_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("target")
+        .join("aoc_watch")
+        .join(format!("day_{}", day));
+    fs::create_dir_all(&run_dir).unwrap();
+
+    let mut watched_files = vec![(PathBuf::from(input), format!("Input_Day_{}", day))];
+    for file in extra_files {
+        let path = PathBuf::from(file);
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        watched_files.push((path, name));
+    }
+
+    let mut last_modified = vec![];
+    let mut last_answers: Option<Vec<String>> = None;
+    let mut run_count = 0;
+
+    loop {
+        let modified = watched_files
+            .iter()
+            .map(|(path, _)| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect::<Vec<_>>();
+
+        if modified != last_modified {
+            last_modified = modified;
+
+            let copy_errors = watched_files
+                .iter()
+                .filter_map(|(path, name)| {
+                    fs::copy(path, run_dir.join(name))
+                        .err()
+                        .map(|err| format!("{}: {}", path.display(), err))
+                })
+                .collect::<Vec<_>>();
+
+            if !copy_errors.is_empty() {
+                for err in copy_errors {
+                    eprintln!("Can't read watched file {}", err);
+                }
+                eprintln!("Waiting for the watched files before running day_{}", day);
+
+                thread::sleep(WATCH_POLL_INTERVAL);
+                continue;
+            }
+
+            run_count += 1;
+            println!("=== Run {} of day_{} ===", run_count, day);
+
+            let output = day_command(day)
+                .args(day_args)
+                .current_dir(&run_dir)
+                .output()
+                .unwrap();
+            let answers = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            if !output.status.success() {
+                eprint!("{}", String::from_utf8_lossy(&output.stderr));
+                eprintln!("day_{} exited with {}", day, output.status);
+            }
+
+            match &last_answers {
+                Some(last_answers) => print_answer_diff(last_answers, &answers),
+                None => answers.iter().for_each(|line| println!("  {}", line)),
+            }
+
+            last_answers = Some(answers);
+        }
+
+        thread::sleep(WATCH_POLL_INTERVAL);
+    }
+}
+
+fn print_answer_diff(old: &[String], new: &[String]) {
+    let mut changed_count = 0;
+
+    for i in 0..old.len().max(new.len()) {
+        match (old.get(i), new.get(i)) {
+            (Some(old_line), Some(new_line)) if old_line == new_line => (),
+            (old_line, new_line) => {
+                changed_count += 1;
+
+                if let Some(old_line) = old_line {
+                    println!("- {}", old_line);
+                }
+                if let Some(new_line) = new_line {
+                    println!("+ {}", new_line);
+                }
+            }
+        }
+    }
+
+    if changed_count == 0 {
+        println!("  No answers changed");
+    }
+}
@@ -1,36 +1,510 @@
-use std::{fs, io};
+use std::cmp::Reverse;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::{env, fs, io, process};
+
+/// How many of the largest per-pair distances the reconciliation report lists
+const LARGEST_DISTANCE_COUNT: usize = 100;
+const HISTOGRAM_BUCKET_COUNT: u64 = 20;
 
 fn main() {
-    let (left_list, right_list) = get_sorted_lists().unwrap();
+    let mut path = "Input_Day_1".to_string();
+    let mut delimiter = None;
+    let mut column_pair = None;
+    let mut all_pairs = false;
+    let mut reconcile_dir = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--delimiter" => match args.next().as_deref() {
+                Some("tab") => delimiter = Some("\t".to_string()),
+                Some(d) if !d.is_empty() => delimiter = Some(d.to_string()),
+                _ => print_usage_and_exit(),
+            },
+            "--columns" => match args.next().as_deref().and_then(parse_column_pair) {
+                Some(pair) => column_pair = Some(pair),
+                None => print_usage_and_exit(),
+            },
+            "--all-pairs" => all_pairs = true,
+            "--reconcile" => match args.next() {
+                Some(dir) => reconcile_dir = Some(dir),
+                None => print_usage_and_exit(),
+            },
+            _ if arg.starts_with("--") => print_usage_and_exit(),
+            _ => path = arg,
+        }
+    }
+
+    let lists = match get_sorted_lists(&path, delimiter.as_deref()) {
+        Ok(lists) => lists,
+        Err(err) => {
+            eprintln!("Couldn't read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    if all_pairs {
+        for lhs in 0..lists.len() {
+            for rhs in lhs + 1..lists.len() {
+                println!(
+                    "Columns {} & {}: Distance: {}, Similarity Score: {}",
+                    lhs + 1,
+                    rhs + 1,
+                    total_distance(&lists[lhs], &lists[rhs]),
+                    similarity_score(&lists[lhs], &lists[rhs])
+                );
+            }
+        }
+
+        return;
+    }
+
+    let (lhs, rhs) = column_pair.unwrap_or((0, 1));
+    let (Some(left_list), Some(right_list)) = (lists.get(lhs), lists.get(rhs)) else {
+        eprintln!("Input only has {} columns", lists.len());
+        process::exit(1);
+    };
+
+    println!("Distance: {}", total_distance(left_list, right_list));
+    println!(
+        "Similarity Score: {}",
+        similarity_score(left_list, right_list)
+    );
+
+    if let Some(dir) = reconcile_dir {
+        match write_reconciliation(Path::new(&dir), left_list, right_list) {
+            Ok(()) => println!("Wrote reconciliation report to {}", dir),
+            Err(err) => {
+                eprintln!("Couldn't write reconciliation report: {}", err);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+fn print_usage_and_exit() -> ! {
+    eprintln!(
+        "Usage: day_1 [input | -] [--delimiter <str | tab>] [--columns <a>,<b> | --all-pairs]"
+    );
+    eprintln!("          [--reconcile <output dir>]");
+    eprintln!("Columns are numbered from 1, the default compares columns 1 and 2");
+    process::exit(1);
+}
+
+/// Parses a 1-based `a,b` column pair into 0-based indices
+fn parse_column_pair(pair: &str) -> Option<(usize, usize)> {
+    let (lhs, rhs) = pair.split_once(',')?;
+    let lhs = lhs.trim().parse::<usize>().ok()?.checked_sub(1)?;
+    let rhs = rhs.trim().parse::<usize>().ok()?.checked_sub(1)?;
 
-    let total_distance: u32 = left_list
+    Some((lhs, rhs))
+}
+
+fn total_distance(left_list: &[i64], right_list: &[i64]) -> u64 {
+    left_list
         .iter()
         .zip(right_list.iter())
         .map(|(lhs, rhs)| lhs.abs_diff(*rhs))
-        .sum();
+        .sum()
+}
+
+fn similarity_score(left_list: &[i64], right_list: &[i64]) -> i64 {
+    value_counts(left_list, right_list)
+        .iter()
+        .map(ValueCount::contribution)
+        .sum()
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ValueCount {
+    value: i64,
+    left_count: usize,
+    right_count: usize,
+}
 
-    println!("Distance: {}", total_distance);
+impl ValueCount {
+    pub fn contribution(&self) -> i64 {
+        self.value * self.left_count as i64 * self.right_count as i64
+    }
+}
+
+/// Merge joins the sorted lists, so each run of equal values is only looked at once, returning
+/// how often every value shows up on each side in ascending order
+fn value_counts(left_list: &[i64], right_list: &[i64]) -> Vec<ValueCount> {
+    let run_len = |list: &[i64], val: i64| list.iter().take_while(|v| **v == val).count();
+
+    let mut counts = vec![];
+    let mut left_index = 0;
+    let mut right_index = 0;
+
+    loop {
+        let (value, left_count, right_count) =
+            match (left_list.get(left_index), right_list.get(right_index)) {
+                (None, None) => break,
+                (Some(&lhs), Some(&rhs)) if lhs == rhs => (
+                    lhs,
+                    run_len(&left_list[left_index..], lhs),
+                    run_len(&right_list[right_index..], lhs),
+                ),
+                (Some(&lhs), Some(&rhs)) if lhs < rhs => {
+                    (lhs, run_len(&left_list[left_index..], lhs), 0)
+                }
+                (Some(&lhs), None) => (lhs, run_len(&left_list[left_index..], lhs), 0),
+                (_, Some(&rhs)) => (rhs, 0, run_len(&right_list[right_index..], rhs)),
+            };
+
+        counts.push(ValueCount {
+            value,
+            left_count,
+            right_count,
+        });
+
+        left_index += left_count;
+        right_index += right_count;
+    }
+
+    counts
+}
+
+/// Writes the CSV files from `reconciliation_csvs` into the directory
+fn write_reconciliation(dir: &Path, left_list: &[i64], right_list: &[i64]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    for (name, contents) in reconciliation_csvs(left_list, right_list) {
+        fs::write(dir.join(name), contents)?;
+    }
 
-    let similarity_score: usize = left_list
+    Ok(())
+}
+
+/// Builds the CSV files explaining where the two sorted lists differ, named:
+/// - `largest_distances.csv`: the pairs furthest apart once both lists are sorted
+/// - `one_sided_values.csv`: values that only show up in one of the lists
+/// - `similarity_contributions.csv`: what each value adds to the similarity score
+/// - `distance_histogram.csv`: how the per-pair distances are spread out
+fn reconciliation_csvs(left_list: &[i64], right_list: &[i64]) -> Vec<(&'static str, String)> {
+    let mut distances = left_list
         .iter()
-        .map(|val| *val as usize * right_list.iter().filter(|v| **v == *val).count())
-        .sum();
+        .zip(right_list.iter())
+        .map(|(lhs, rhs)| lhs.abs_diff(*rhs))
+        .enumerate()
+        .collect::<Vec<_>>();
+    distances.sort_by_key(|(i, distance)| (Reverse(*distance), *i));
+
+    let mut largest_distances = String::from("rank,index,left,right,distance\n");
+    for (rank, (i, distance)) in distances.iter().take(LARGEST_DISTANCE_COUNT).enumerate() {
+        largest_distances.push_str(&format!(
+            "{},{},{},{},{}\n",
+            rank + 1,
+            i,
+            left_list[*i],
+            right_list[*i],
+            distance
+        ));
+    }
+
+    let counts = value_counts(left_list, right_list);
+
+    let mut one_sided_values = String::from("value,side,count\n");
+    for count in &counts {
+        match (count.left_count, count.right_count) {
+            (left_count, 0) => {
+                one_sided_values.push_str(&format!("{},left,{}\n", count.value, left_count))
+            }
+            (0, right_count) => {
+                one_sided_values.push_str(&format!("{},right,{}\n", count.value, right_count))
+            }
+            _ => (),
+        }
+    }
+
+    let mut similarity_contributions = String::from("value,left_count,right_count,contribution\n");
+    for count in counts.iter().filter(|c| c.left_count > 0) {
+        similarity_contributions.push_str(&format!(
+            "{},{},{},{}\n",
+            count.value,
+            count.left_count,
+            count.right_count,
+            count.contribution()
+        ));
+    }
+
+    let max_distance = distances.first().map(|(_, d)| *d).unwrap_or(0);
+    let bucket_width = (max_distance / HISTOGRAM_BUCKET_COUNT + 1).max(1);
+    let mut histogram = vec![0; (max_distance / bucket_width + 1) as usize];
+    for (_, distance) in &distances {
+        histogram[(distance / bucket_width) as usize] += 1;
+    }
+
+    let mut distance_histogram = String::from("min_distance,max_distance,count\n");
+    for (i, count) in histogram.iter().enumerate() {
+        let min_distance = i as u64 * bucket_width;
+        distance_histogram.push_str(&format!(
+            "{},{},{}\n",
+            min_distance,
+            min_distance + bucket_width - 1,
+            count
+        ));
+    }
 
-    println!("Similarity Score: {}", similarity_score);
+    vec![
+        ("largest_distances.csv", largest_distances),
+        ("one_sided_values.csv", one_sided_values),
+        ("similarity_contributions.csv", similarity_contributions),
+        ("distance_histogram.csv", distance_histogram),
+    ]
 }
 
-fn get_sorted_lists() -> io::Result<(Vec<i32>, Vec<i32>)> {
-    let (mut left_list, mut right_list) = get_lists()?;
-    left_list.sort();
-    right_list.sort();
+fn get_sorted_lists(path: &str, delimiter: Option<&str>) -> io::Result<Vec<Vec<i64>>> {
+    let mut lists = get_lists(path, delimiter)?;
+    lists.iter_mut().for_each(|list| list.sort());
 
-    Ok((left_list, right_list))
+    Ok(lists)
 }
 
-fn get_lists() -> io::Result<(Vec<i32>, Vec<i32>)> {
-    Ok(fs::read_to_string("Input_Day_1")?
-        .lines()
-        .map(|line| line.split_once("   ").unwrap())
-        .map(|(lhs, rhs)| (lhs.parse::<i32>().unwrap(), rhs.parse::<i32>().unwrap()))
-        .unzip())
+/// Reads the lists from a file, or from stdin when the path is `-`
+fn get_lists(path: &str, delimiter: Option<&str>) -> io::Result<Vec<Vec<i64>>> {
+    if path == "-" {
+        read_lists(io::stdin().lock(), delimiter)
+    } else {
+        read_lists(BufReader::new(File::open(path)?), delimiter)
+    }
+}
+
+/// Streams the columns in line by line rather than loading the whole input first.
+///
+/// Columns are split on any run of whitespace unless a delimiter is given, every line needs
+/// the same number of columns as the first one
+fn read_lists(reader: impl BufRead, delimiter: Option<&str>) -> io::Result<Vec<Vec<i64>>> {
+    let mut lists: Vec<Vec<i64>> = vec![];
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = match delimiter {
+            Some(delimiter) => line.split(delimiter).map(str::trim).collect(),
+            None => line.split_whitespace().collect(),
+        };
+
+        if lists.is_empty() {
+            lists = vec![vec![]; fields.len()];
+        }
+
+        if fields.len() != lists.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "line {} has {} columns, expected {}",
+                    i + 1,
+                    fields.len(),
+                    lists.len()
+                ),
+            ));
+        }
+
+        for (list, field) in lists.iter_mut().zip(fields) {
+            let val = field.parse::<i64>().map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {:?} {}", i + 1, field, err),
+                )
+            })?;
+
+            list.push(val);
+        }
+    }
+
+    Ok(lists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_lists() -> (Vec<i64>, Vec<i64>) {
+        let mut left_list = vec![3, 4, 2, 1, 3, 3];
+        let mut right_list = vec![4, 3, 5, 3, 9, 3];
+        left_list.sort();
+        right_list.sort();
+
+        (left_list, right_list)
+    }
+
+    #[test]
+    fn example() {
+        let (left_list, right_list) = example_lists();
+
+        assert_eq!(total_distance(&left_list, &right_list), 11);
+        assert_eq!(similarity_score(&left_list, &right_list), 31);
+    }
+
+    #[test]
+    fn similarity_score_matches_filter_and_count() {
+        let pairs = [
+            (vec![3, 3, 3, 1, 7, 7], vec![3, 3, 7, 9, 9, 2]),
+            (vec![-5, -5, 0, 2, 2, 2], vec![-5, 2, 2, 4, -1, -5]),
+            (vec![1, 2, 3], vec![4, 5, 6]),
+            (vec![8, 8, 8, 8], vec![8, 8]),
+            (vec![-3, 10], vec![10, 10, 10, -3, -3]),
+            (vec![], vec![1, 1]),
+        ];
+
+        for (mut left_list, mut right_list) in pairs {
+            left_list.sort();
+            right_list.sort();
+
+            let expected: i64 = left_list
+                .iter()
+                .map(|lhs| lhs * right_list.iter().filter(|rhs| *rhs == lhs).count() as i64)
+                .sum();
+
+            assert_eq!(
+                similarity_score(&left_list, &right_list),
+                expected,
+                "{:?} {:?}",
+                left_list,
+                right_list
+            );
+        }
+    }
+
+    #[test]
+    fn read_lists_splits_on_whitespace() {
+        let input = "3   4\n4\t\t3\n\n 2 \t 5 \n";
+
+        let lists = read_lists(input.as_bytes(), None).unwrap();
+
+        assert_eq!(lists, [vec![3, 4, 2], vec![4, 3, 5]]);
+    }
+
+    #[test]
+    fn read_lists_with_delimiter() {
+        let lists = read_lists("1, 2\n3 ,4\n".as_bytes(), Some(",")).unwrap();
+        assert_eq!(lists, [vec![1, 3], vec![2, 4]]);
+
+        let lists = read_lists("1\t2\n3\t4\n".as_bytes(), Some("\t")).unwrap();
+        assert_eq!(lists, [vec![1, 3], vec![2, 4]]);
+    }
+
+    #[test]
+    fn read_lists_with_many_columns_and_large_values() {
+        let input = "1 -2 9000000000\n-4000000000 5 6\n";
+
+        let lists = read_lists(input.as_bytes(), None).unwrap();
+
+        assert_eq!(
+            lists,
+            [vec![1, -4_000_000_000], vec![-2, 5], vec![9_000_000_000, 6]]
+        );
+    }
+
+    #[test]
+    fn read_lists_rejects_bad_rows() {
+        let err = read_lists("1 2\n3 4 5\n".as_bytes(), None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 2 has 3 columns, expected 2");
+
+        let err = read_lists("1 2\n3 x\n".as_bytes(), None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2: \"x\""));
+
+        let err = read_lists("1;2\n".as_bytes(), Some(",")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn column_pairs() {
+        assert_eq!(parse_column_pair("1,2"), Some((0, 1)));
+        assert_eq!(parse_column_pair(" 3 , 1 "), Some((2, 0)));
+        assert_eq!(parse_column_pair("0,1"), None);
+        assert_eq!(parse_column_pair("a,b"), None);
+        assert_eq!(parse_column_pair("1"), None);
+    }
+
+    #[test]
+    fn example_reconciliation() {
+        let (left_list, right_list) = example_lists();
+
+        let csvs = reconciliation_csvs(&left_list, &right_list);
+        let names = csvs.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            [
+                "largest_distances.csv",
+                "one_sided_values.csv",
+                "similarity_contributions.csv",
+                "distance_histogram.csv"
+            ]
+        );
+
+        // Sorted: left 1,2,3,3,3,4 and right 3,3,3,4,5,9
+        assert_eq!(
+            csvs[0].1,
+            "rank,index,left,right,distance
+1,5,4,9,5
+2,0,1,3,2
+3,4,3,5,2
+4,1,2,3,1
+5,3,3,4,1
+6,2,3,3,0
+"
+        );
+        assert_eq!(
+            csvs[1].1,
+            "value,side,count
+1,left,1
+2,left,1
+5,right,1
+9,right,1
+"
+        );
+        assert_eq!(
+            csvs[2].1,
+            "value,left_count,right_count,contribution
+1,1,0,0
+2,1,0,0
+3,3,3,27
+4,1,1,4
+"
+        );
+        // Max distance 5 over 20 buckets rounds up to buckets 1 wide
+        assert_eq!(
+            csvs[3].1,
+            "min_distance,max_distance,count
+0,0,1
+1,1,2
+2,2,2
+3,3,0
+4,4,0
+5,5,1
+"
+        );
+    }
+
+    #[test]
+    fn histogram_buckets_cover_max_distance() {
+        let left_list = (0..50).collect::<Vec<i64>>();
+        let right_list = (0..50).map(|i| i * 3).collect::<Vec<i64>>();
+
+        let csvs = reconciliation_csvs(&left_list, &right_list);
+        let histogram = &csvs[3].1;
+
+        // Distances run 0..=98, so 20 buckets of width 5 with the last holding 95..=99
+        assert_eq!(histogram.lines().count(), 21);
+        assert_eq!(histogram.lines().nth(1), Some("0,4,3"));
+        assert_eq!(histogram.lines().last(), Some("95,99,2"));
+
+        let total = histogram
+            .lines()
+            .skip(1)
+            .map(|line| line.rsplit(',').next().unwrap().parse::<usize>().unwrap())
+            .sum::<usize>();
+        assert_eq!(total, 50);
+    }
 }
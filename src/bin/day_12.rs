@@ -1,7 +1,31 @@
 use std::collections::HashSet;
-use std::fs;
+use std::{env, fs};
+
+#[path = "../repl.rs"]
+mod repl;
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("repl") => {
+            let path = args.get(1).map(String::as_str).unwrap_or("Input_Day_12");
+            let input = fs::read_to_string(path).unwrap();
+            repl(Farm::new(&input));
+            return;
+        }
+        Some("report") => {
+            let path = args
+                .get(1)
+                .map(String::as_str)
+                .unwrap_or("Report_Day_12.md");
+            let input = fs::read_to_string("Input_Day_12").unwrap();
+            fs::write(path, report(&Farm::new(&input))).unwrap();
+            return;
+        }
+        _ => (),
+    }
+
     let input = fs::read_to_string("Input_Day_12").unwrap();
     let farm = Farm::new(&input);
     let regions = farm.get_regions();
@@ -253,6 +277,14 @@ impl Farm {
         }
     }
 
+    /// Looks up a plot from the user supplied coordinates
+    pub fn get_plot(&self, x: &str, y: &str) -> Option<Plot> {
+        let x = x.parse::<usize>().ok()?;
+        let y = y.parse::<usize>().ok()?;
+
+        self.plots.get(y)?.get(x).copied()
+    }
+
     pub fn get_regions(&self) -> Vec<Region> {
         let mut regions = vec![];
 
@@ -316,3 +348,110 @@ impl Farm {
         )
     }
 }
+
+/// Builds a markdown report with the area, perimeter and side count of every region
+fn report(farm: &Farm) -> String {
+    let regions = farm.get_regions();
+
+    let mut out = String::from("# Day 12: Garden Groups\n\n");
+    out.push_str("| Plant | Origin | Area | Perimeter | Sides | Price | Discount Price |\n");
+    out.push_str("|---|---|---:|---:|---:|---:|---:|\n");
+
+    let mut price = 0;
+    let mut discount_price = 0;
+
+    for region in &regions {
+        let origin = region.plots.iter().min().unwrap();
+        let sides = region.side_count();
+
+        price += region.area * region.perimeter;
+        discount_price += region.area * sides;
+
+        out.push_str(&format!(
+            "| {} | ({}, {}) | {} | {} | {} | {} | {} |\n",
+            origin.plant_type,
+            origin.pos.x,
+            origin.pos.y,
+            region.area,
+            region.perimeter,
+            sides,
+            region.area * region.perimeter,
+            region.area * sides
+        ));
+    }
+
+    out.push_str(&format!("\n{} regions\n\n", regions.len()));
+    out.push_str(&format!("- Price: ${}\n", price));
+    out.push_str(&format!("- Discount Price: ${}\n", discount_price));
+
+    out
+}
+
+fn repl(mut farm: Farm) {
+    println!(
+        "Loaded {}x{} farm, type 'help' for commands",
+        farm.width, farm.height
+    );
+    repl::run_commands(|words| {
+        match words {
+            ["help"] => {
+                println!("show                   print the farm");
+                println!("query <x> <y>          area, perimeter and sides of the plot's region");
+                println!("part1 | part2          fence prices for the whole farm");
+                println!("set <x> <y> <plant>    replace the plant in a single plot");
+                println!("quit");
+            }
+            ["show"] => {
+                for row in &farm.plots {
+                    println!("{}", row.iter().map(|p| p.plant_type).collect::<String>());
+                }
+            }
+            ["query", x, y] => match farm.get_plot(x, y) {
+                Some(plot) => {
+                    let (region, _) = farm.get_region(plot);
+                    println!(
+                        "Region '{}': area {}, perimeter {}, sides {}",
+                        plot.plant_type,
+                        region.area,
+                        region.perimeter,
+                        region.side_count()
+                    );
+                }
+                None => println!("Invalid position: {} {}", x, y),
+            },
+            ["part1"] => {
+                let price: u64 = farm
+                    .get_regions()
+                    .iter()
+                    .map(|r| r.area * r.perimeter)
+                    .sum();
+                println!("Price: ${}", price);
+            }
+            ["part2"] => {
+                let discount_price: u64 = farm
+                    .get_regions()
+                    .iter()
+                    .map(|r| r.area * r.side_count())
+                    .sum();
+                println!("Discount Price: ${}", discount_price);
+            }
+            ["set", x, y, plant] => {
+                let plant_type = match plant.chars().collect::<Vec<_>>().as_slice() {
+                    [c] => Some(*c),
+                    _ => None,
+                };
+
+                match (farm.get_plot(x, y), plant_type) {
+                    (Some(plot), Some(plant_type)) => {
+                        farm.plots[plot.pos.y as usize][plot.pos.x as usize] =
+                            Plot::new(plant_type, plot.pos);
+                    }
+                    _ => println!("Invalid plot: {} {} {}", x, y, plant),
+                }
+            }
+            _ => return false,
+        }
+
+        true
+    });
+}
@@ -1,6 +1,8 @@
-use std::fs;
+use std::{env, fs};
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
     let input = fs::read_to_string("Input_Day_13").unwrap();
 
     let machines: Vec<Machine> = input
@@ -19,6 +21,15 @@ fn main() {
         })
         .collect();
 
+    if let Some("report") = args.first().map(String::as_str) {
+        let path = args
+            .get(1)
+            .map(String::as_str)
+            .unwrap_or("Report_Day_13.md");
+        fs::write(path, report(&machines)).unwrap();
+        return;
+    }
+
     let total_minimum_tokens: f64 = machines.iter().flat_map(|m| m.minimum_tokens()).sum();
     println!("Total Minimum Tokens: {}", total_minimum_tokens);
 
@@ -29,6 +40,57 @@ fn main() {
     println!("Total Minimum Tokens 2: {}", total_minimum_tokens_part_2);
 }
 
+/// Builds a markdown report with the button presses each machine needs in both parts
+fn report(machines: &[Machine]) -> String {
+    let format_presses = |presses: Option<(f64, f64)>| match presses {
+        Some((a, b)) => format!("{} | {} | {}", a, b, a * 3.0 + b),
+        None => "- | - | -".to_string(),
+    };
+    let format_coord = |(x, y): (f64, f64)| format!("({}, {})", x, y);
+
+    let mut out = String::from("# Day 13: Claw Contraption\n\n");
+    out.push_str("| Machine | A | B | Prize | A Presses | B Presses | Tokens | A Presses 2 | B Presses 2 | Tokens 2 |\n");
+    out.push_str("|---:|---|---|---|---:|---:|---:|---:|---:|---:|\n");
+
+    for (i, machine) in machines.iter().enumerate() {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            i + 1,
+            format_coord(machine.a),
+            format_coord(machine.b),
+            format_coord(machine.prize),
+            format_presses(machine.button_presses()),
+            format_presses(machine.button_presses_2_electric_boogaloo())
+        ));
+    }
+
+    let total_minimum_tokens: f64 = machines.iter().flat_map(|m| m.minimum_tokens()).sum();
+    let total_minimum_tokens_part_2: f64 = machines
+        .iter()
+        .flat_map(|m| m.minimum_tokens_2_electric_boogaloo())
+        .sum();
+
+    out.push_str(&format!(
+        "\n{} of {} machines can be won in part 1, {} in part 2\n\n",
+        machines.iter().flat_map(|m| m.button_presses()).count(),
+        machines.len(),
+        machines
+            .iter()
+            .flat_map(|m| m.button_presses_2_electric_boogaloo())
+            .count()
+    ));
+    out.push_str(&format!(
+        "- Total Minimum Tokens: {}\n",
+        total_minimum_tokens
+    ));
+    out.push_str(&format!(
+        "- Total Minimum Tokens 2: {}\n",
+        total_minimum_tokens_part_2
+    ));
+
+    out
+}
+
 fn coordinate_parser(str: &str) -> (f64, f64) {
     let (x_str, y_str) = str.split_once(", ").unwrap();
     let x = x_str
@@ -62,6 +124,16 @@ impl Machine {
     }
 
     pub fn minimum_tokens(&self) -> Option<f64> {
+        self.button_presses().map(|(a, b)| a * 3.0 + b)
+    }
+
+    pub fn minimum_tokens_2_electric_boogaloo(&self) -> Option<f64> {
+        self.button_presses_2_electric_boogaloo()
+            .map(|(a, b)| a * 3.0 + b)
+    }
+
+    /// Returns the (A, B) button presses needed to win the prize, if it can be won
+    pub fn button_presses(&self) -> Option<(f64, f64)> {
         let det = self.a.0 * self.b.1 - self.b.0 * self.a.1;
 
         // adj
@@ -84,7 +156,7 @@ impl Machine {
         if a < 0.0 || b < 0.0 || a > 100.0 || b > 100.0 {
             None
         } else {
-            Some(a * 3.0 + b)
+            Some((a, b))
         }
 
         // println!("Det: {}", det);
@@ -92,7 +164,7 @@ impl Machine {
         // println!("A: {}, B: {}", a, b);
     }
 
-    pub fn minimum_tokens_2_electric_boogaloo(&self) -> Option<f64> {
+    pub fn button_presses_2_electric_boogaloo(&self) -> Option<(f64, f64)> {
         let det = self.a.0 * self.b.1 - self.b.0 * self.a.1;
         let prize = (
             self.prize.0 + 10000000000000.0,
@@ -119,7 +191,7 @@ impl Machine {
         if a < 100.0 || b < 100.0 {
             None
         } else {
-            Some(a * 3.0 + b)
+            Some((a, b))
         }
     }
 }
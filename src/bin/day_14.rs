@@ -1,5 +1,10 @@
-use std::fs;
+use std::fmt::{Display, Formatter};
 use std::ops::{Add, Mul};
+use std::fs;
+
+#[path = "../deadline.rs"]
+mod deadline;
+use deadline::Deadline;
 
 fn main() {
     let input = fs::read_to_string("Input_Day_14").unwrap();
@@ -33,7 +38,17 @@ fn main() {
     let mut seconds = 0;
     // let mut total_score = score;
     // let mut total_count = 1;
+    let deadline = Deadline::from_env();
     loop {
+        if deadline.is_expired() {
+            println!(
+                "Tree search timed out after {:?} ({} seconds tried)",
+                deadline.elapsed(),
+                seconds
+            );
+            break;
+        }
+
         seconds += 1;
         println!("Trying ({}) seconds", seconds);
 
@@ -194,15 +209,7 @@ impl Board {
     }
 
     pub fn print(&self) {
-        let mut chars = self.output;
-
-        // chars[51][50] = '^';
-
-        for line in chars {
-            let str = line.iter().collect::<String>();
-            println!("{}", str);
-        }
-        println!();
+        println!("{}\n", self);
     }
 
     pub fn is_tree(&self) -> bool {
@@ -308,3 +315,53 @@ impl Board {
         false
     }
 }
+
+impl Display for Board {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let out = self
+            .output
+            .iter()
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        write!(f, "{}", out)
+    }
+}
+
+#[cfg(test)]
+#[path = "../snapshot.rs"]
+mod snapshot;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::assert_snapshot;
+
+    const EXAMPLE: &str = "p=0,4 v=3,-3
+p=6,3 v=-1,-3
+p=10,3 v=-1,2
+p=2,0 v=2,-1
+p=0,0 v=1,3
+p=3,0 v=-2,-2
+p=7,6 v=-1,-3
+p=3,0 v=-1,-2
+p=9,3 v=2,3
+p=7,3 v=-1,2
+p=2,4 v=2,-3
+p=9,5 v=-3,-3";
+
+    #[test]
+    fn board_snapshot() {
+        let robots = parse_input(EXAMPLE)
+            .iter()
+            .map(|r| {
+                let mut r = r.fast_forward(100);
+                r.correct_position();
+                r
+            })
+            .collect::<Vec<_>>();
+
+        assert_snapshot("day_14_board", &Board::new(&robots).to_string());
+    }
+}
@@ -495,3 +495,65 @@ impl WideSimulator {
         }
     }
 }
+
+#[cfg(test)]
+#[path = "../snapshot.rs"]
+mod snapshot;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::assert_snapshot;
+
+    const EXAMPLE: &str = "##########
+#..O..O.O#
+#......O.#
+#.OO..O.O#
+#..O@..O.#
+#O#..O...#
+#O..O..O.#
+#.OO.O.OO#
+#....O...#
+##########
+
+<vv>^<v^>v>^vv^v>v<>v^v<v<^vv<<<^><<><>>v<vvv<>^v^>^<<<><<v<<<v^vv^v>^
+vvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v
+><>vv>v^v^<>><>>>><^^>vv>v<^^^>>v^v^<^^>v^^>v^<^v>v<>>v^v^<v>v^^<^^vv<
+<<v<^>>^^^^>>>v^<>vvv^><v<<<>^^^vv^<vvv>^>v<^^^^v<>^>vvvv><>>v^<<^^^^^
+^><^><>>><>^^<<^^v>>><^<v>^<vv>>v>>>^v><>^v><<<<v>>v<v<v>vvv>^<><<>^><
+^>><>^v<><^vvv<^^<><v<<<<<><^v<<<><<<^^<v<^^^><^>>^<v^><<<^>>^v<v^v<v^
+>^>>^v>vv>^<<^v<>><<><<v<<v><>v<^vv<<<>^^v^>^^>>><<^v>>v^v><^^>>^<>vv^
+<><^^>^^^<><vvvvv^v<v<<>^v<v>v<<^><<><<><<<^^<<<^<<>><<><^^^>^^<>^>v<>
+^^>vv<^v^v<vv>^<><v<^v>^^^>>>^^vvv^>vvv<>>>^<^>>>>>^<<^v>^vvv<>^<><<v>
+v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
+
+    fn example_boards() -> (Board, WideBoard, MoveList) {
+        let (board_str, moves_str) = EXAMPLE.split_once("\n\n").unwrap();
+        let board = Board::new(board_str);
+        let wide_board = WideBoard::new(&board);
+        let move_list = MoveList::try_new(moves_str).unwrap();
+
+        (board, wide_board, move_list)
+    }
+
+    #[test]
+    fn board_snapshots() {
+        let (board, _, move_list) = example_boards();
+        assert_snapshot("day_15_board", &board.to_string());
+
+        let finished_board = Simulator::simulate(board, &move_list);
+        assert_snapshot("day_15_board_finished", &finished_board.to_string());
+    }
+
+    #[test]
+    fn wide_board_snapshots() {
+        let (_, wide_board, move_list) = example_boards();
+        assert_snapshot("day_15_wide_board", &wide_board.to_string());
+
+        let finished_wide_board = WideSimulator::simulate(wide_board, &move_list);
+        assert_snapshot(
+            "day_15_wide_board_finished",
+            &finished_wide_board.to_string(),
+        );
+    }
+}
@@ -1,19 +1,47 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
-use std::fs;
+use std::{env, fs};
+
+#[path = "../deadline.rs"]
+mod deadline;
+#[path = "../repl.rs"]
+mod repl;
+
+use deadline::Deadline;
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Some("repl") = args.first().map(String::as_str) {
+        let path = args.get(1).map(String::as_str).unwrap_or("Input_Day_16");
+        let input = fs::read_to_string(path).unwrap();
+        repl(Maze::new(&input));
+        return;
+    }
+
     let input = fs::read_to_string("Input_Day_16").unwrap();
     let mut maze = Maze::new(&input);
 
     maze.fill_deadends();
 
     // let lowest_score = maze.get_lowest_score((135, 1), Direction::East);
-    let (lowest_score, best_cells) = maze.get_lowest_maze_score();
+    let deadline = Deadline::from_env();
+    let result = maze.get_lowest_maze_score(&deadline);
     println!("{}\n", Maze::new(&input));
     println!("{}\n", maze);
-    println!("Lowest Score: {}", lowest_score);
-    println!("Best Cell Count: {}", best_cells);
+
+    match result {
+        Ok(Some((lowest_score, best_cells))) => {
+            println!("Lowest Score: {}", lowest_score);
+            println!("Best Cell Count: {}", best_cells);
+        }
+        Ok(None) => println!("Lowest Score: the end can't be reached"),
+        Err(explored_count) => println!(
+            "Lowest Score: timed out after {:?} ({} reindeer states explored)",
+            deadline.elapsed(),
+            explored_count
+        ),
+    }
 }
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
@@ -25,6 +53,16 @@ enum Direction {
 }
 
 impl Direction {
+    pub fn try_new(name: &str) -> Option<Self> {
+        match name {
+            "north" | "n" => Some(Self::North),
+            "south" | "s" => Some(Self::South),
+            "east" | "e" => Some(Self::East),
+            "west" | "w" => Some(Self::West),
+            _ => None,
+        }
+    }
+
     pub fn score_mod(&self, dir: Self) -> u64 {
         match (*self, dir) {
             (Self::North, Self::North)
@@ -79,6 +117,7 @@ impl Reindeer {
     }
 }
 
+#[derive(Clone)]
 struct Maze {
     grid: Box<[Box<[char]>]>,
     start: (usize, usize),
@@ -116,6 +155,14 @@ impl Maze {
         }
     }
 
+    /// Looks up a cell from the user supplied coordinates
+    pub fn get_cell(&self, x: &str, y: &str) -> Option<(usize, usize)> {
+        let x = x.parse::<usize>().ok()?;
+        let y = y.parse::<usize>().ok()?;
+
+        self.grid.get(y)?.get(x).map(|_| (x, y))
+    }
+
     pub fn fill_deadends(&mut self) {
         fn inner(maze: &mut Maze) -> u64 {
             let mut deadend_list = vec![];
@@ -144,11 +191,20 @@ impl Maze {
         while inner(self) != 0 {}
     }
 
+    /// Sets `timed_out` if the deadline cut the search short, so the reindeer found are
+    /// incomplete
     fn get_lowest_score(
         &self,
         mut reindeer: Reindeer,
         cache: &mut HashMap<((usize, usize), Direction), u64>,
+        deadline: &Deadline,
+        timed_out: &mut bool,
     ) -> Vec<Reindeer> {
+        if deadline.is_expired() {
+            *timed_out = true;
+            return vec![];
+        }
+
         if reindeer.visited.contains(&reindeer.pos) {
             return vec![];
         }
@@ -180,22 +236,36 @@ impl Maze {
                 let reindeer_vec = vec![north, south, east, west];
                 reindeer_vec
                     .into_iter()
-                    .flat_map(|r| self.get_lowest_score(r, cache))
+                    .flat_map(|r| self.get_lowest_score(r, cache, deadline, timed_out))
                     .collect()
             }
             _ => unreachable!(),
         }
     }
 
-    pub fn get_lowest_maze_score(&mut self) -> (u64, usize) {
+    /// Returns the lowest score and best cell count, `None` if no reindeer reaches the end, or
+    /// the number of explored reindeer states if the deadline expired first
+    pub fn get_lowest_maze_score(
+        &mut self,
+        deadline: &Deadline,
+    ) -> Result<Option<(u64, usize)>, usize> {
         let mut score_cache = HashMap::new();
+        let mut timed_out = false;
 
         let reindeer = Reindeer::new(self.start, Direction::East);
-        let finished_reindeer = self.get_lowest_score(reindeer, &mut score_cache);
-        let r = finished_reindeer
+        let finished_reindeer =
+            self.get_lowest_score(reindeer, &mut score_cache, deadline, &mut timed_out);
+
+        if timed_out {
+            return Err(score_cache.len());
+        }
+
+        let Some(r) = finished_reindeer
             .iter()
             .min_by(|lhs, rhs| lhs.current_score.cmp(&rhs.current_score))
-            .unwrap();
+        else {
+            return Ok(None);
+        };
 
         let lowest_score = r.current_score;
         let set = finished_reindeer
@@ -204,7 +274,7 @@ impl Maze {
             .map(|r| r.visited.clone())
             .reduce(|a, b| a.union(&b).map(|e| *e).collect::<HashSet<_>>())
             .unwrap();
-        (lowest_score, set.len() + 1) // End cell wasnt counted by asexually reproducing reindeer
+        Ok(Some((lowest_score, set.len() + 1))) // End cell wasnt counted by asexually reproducing reindeer
     }
 }
 
@@ -223,3 +293,136 @@ impl Display for Maze {
         write!(f, "{}", out)
     }
 }
+
+fn repl(original_maze: Maze) {
+    let mut maze = original_maze.clone();
+
+    println!(
+        "Loaded {}x{} maze, type 'help' for commands",
+        maze.grid[0].len(),
+        maze.grid.len()
+    );
+    repl::run_commands(|words| {
+        match words {
+            ["help"] => {
+                println!("show                     print the maze");
+                println!("fill                     fill in dead ends");
+                println!("query <x> <y>            describe a single cell");
+                println!("score <x> <y> <dir>      lowest score from a cell facing north/south/east/west");
+                println!(
+                    "part1                    lowest score and best cell count from the start"
+                );
+                println!("set start <x> <y>        move the start cell");
+                println!("reset                    restore the original maze");
+                println!("quit");
+            }
+            ["show"] => println!("{}", maze),
+            ["fill"] => maze.fill_deadends(),
+            ["query", x, y] => match maze.get_cell(x, y) {
+                Some((x, y)) => {
+                    let kind = match maze.grid[y][x] {
+                        '#' => "wall",
+                        'S' => "start",
+                        'E' => "end",
+                        _ => "floor",
+                    };
+                    println!("({}, {}): {}", x, y, kind);
+                }
+                None => println!("Invalid position: {} {}", x, y),
+            },
+            ["score", x, y, dir] => match (maze.get_cell(x, y), Direction::try_new(dir)) {
+                (Some(pos), Some(dir)) => {
+                    let deadline = Deadline::from_env();
+                    let mut timed_out = false;
+                    let finished_reindeer = maze.get_lowest_score(
+                        Reindeer::new(pos, dir),
+                        &mut HashMap::new(),
+                        &deadline,
+                        &mut timed_out,
+                    );
+
+                    match finished_reindeer.iter().map(|r| r.current_score).min() {
+                        _ if timed_out => {
+                            println!("Timed out after {:?}", deadline.elapsed())
+                        }
+                        Some(score) => println!("Lowest Score: {}", score),
+                        None => println!("The end can't be reached from ({}, {})", pos.0, pos.1),
+                    }
+                }
+                _ => println!("Invalid reindeer: {} {} {}", x, y, dir),
+            },
+            ["part1"] => match maze.get_lowest_maze_score(&Deadline::from_env()) {
+                Ok(Some((lowest_score, best_cells))) => {
+                    println!("Lowest Score: {}", lowest_score);
+                    println!("Best Cell Count: {}", best_cells);
+                }
+                Ok(None) => println!("The end can't be reached"),
+                Err(explored_count) => {
+                    println!("Timed out ({} reindeer states explored)", explored_count)
+                }
+            },
+            ["set", "start", x, y] => match maze.get_cell(x, y) {
+                Some((x, y)) if !matches!(maze.grid[y][x], '#' | 'E') => {
+                    let (old_x, old_y) = maze.start;
+                    maze.grid[old_y][old_x] = '.';
+                    maze.grid[y][x] = 'S';
+                    maze.start = (x, y);
+                }
+                _ => println!("Invalid start: {} {}", x, y),
+            },
+            ["reset"] => maze = original_maze.clone(),
+            _ => return false,
+        }
+
+        true
+    });
+}
+
+#[cfg(test)]
+#[path = "../snapshot.rs"]
+mod snapshot;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::assert_snapshot;
+
+    const EXAMPLE: &str = "###############
+#.......#....E#
+#.#.###.#.###.#
+#.....#.#...#.#
+#.###.#####.#.#
+#.#.#.......#.#
+#.#.#####.###.#
+#...........#.#
+###.#.#####.#.#
+#...#.....#.#.#
+#.#.#.###.#.#.#
+#.....#...#.#.#
+#.###.#.#.#.#.#
+#S..#.....#...#
+###############";
+
+    #[test]
+    fn maze_snapshots() {
+        let mut maze = Maze::new(EXAMPLE);
+        assert_snapshot("day_16_maze", &maze.to_string());
+
+        maze.fill_deadends();
+        assert_snapshot("day_16_maze_filled", &maze.to_string());
+    }
+
+    #[test]
+    fn unreachable_end_has_no_score() {
+        let mut maze = Maze::new(EXAMPLE);
+        assert_eq!(
+            maze.get_lowest_maze_score(&Deadline::from_env()),
+            Ok(Some((7036, 45)))
+        );
+
+        // Wall the end off
+        maze.grid[2][13] = '#';
+        maze.grid[1][12] = '#';
+        assert_eq!(maze.get_lowest_maze_score(&Deadline::from_env()), Ok(None));
+    }
+}
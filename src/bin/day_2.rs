@@ -1,13 +1,24 @@
-use std::{fs, io};
+use std::cmp::{Ordering, Reverse};
+use std::fmt::{Display, Formatter};
+use std::{env, fs, io, process};
 
 fn main() {
-    // let report = Report::new(&[0, 1, 2, 3, 4, 5]);
-    // let report = Report::new(&[0, 1, 7, 3, 4, 5]);
-    // let report = Report::new(&[0, 1, 2, 3, 2, 5]);
-    // let report = Report::new(&[0, 0, 2, 3, 2, 5]);
-    // let report = Report::new(&[1, 0, 2, 3, 2, 5]); // killer edge case
+    let (policy, explain) = parse_args();
 
-    let reports = get_reports().unwrap();
+    // let report = Report::new(&[0, 1, 2, 3, 4, 5], &policy);
+    // let report = Report::new(&[0, 1, 7, 3, 4, 5], &policy);
+    // let report = Report::new(&[0, 1, 2, 3, 2, 5], &policy);
+    // let report = Report::new(&[0, 0, 2, 3, 2, 5], &policy);
+    // let report = Report::new(&[1, 0, 2, 3, 2, 5], &policy); // killer edge case
+
+    let reports = get_reports(&policy).unwrap();
+
+    if explain {
+        for (i, report) in reports.iter().enumerate() {
+            println!("Report {}: {}", i + 1, report.explain());
+        }
+        println!();
+    }
 
     let safe_report_count = reports.iter().filter(|report| report.is_safe()).count();
     println!("Safe Report Count: {}", safe_report_count);
@@ -18,12 +29,59 @@ fn main() {
         .count();
 
     println!("Kinda Safe Count: {}", kinda_safe_count);
+
+    // Counting removals for unsafe reports needs the full DP, so skip it for the usual dampener
+    if policy.max_removals > 1 || explain {
+        let mut removal_counts = vec![];
+        for removals in reports.iter().map(Report::removals) {
+            if removal_counts.len() <= removals {
+                removal_counts.resize(removals + 1, 0);
+            }
+            removal_counts[removals] += 1;
+        }
+
+        for (removals, count) in removal_counts.iter().enumerate().skip(1) {
+            println!("Reports Needing {} Removals: {}", removals, count);
+        }
+    }
+}
+
+fn parse_args() -> (SafetyPolicy, bool) {
+    let mut policy = SafetyPolicy::default();
+    let mut explain = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "--allow-reversals" => {
+                policy.require_monotonic = false;
+                continue;
+            }
+            "--explain" => {
+                explain = true;
+                continue;
+            }
+            _ => args.next().and_then(|v| v.parse::<u32>().ok()),
+        };
+
+        match (arg.as_str(), value) {
+            ("--min-step", Some(v)) => policy.min_step = v as i64,
+            ("--max-step", Some(v)) => policy.max_step = v as i64,
+            ("--max-removals", Some(v)) => policy.max_removals = v as usize,
+            _ => {
+                eprintln!("Usage: day_2 [--min-step <n>] [--max-step <n>] [--max-removals <n>] [--allow-reversals] [--explain]");
+                process::exit(1);
+            }
+        }
+    }
+
+    (policy, explain)
 }
 
-fn get_reports() -> io::Result<Vec<Report>> {
+fn get_reports(policy: &SafetyPolicy) -> io::Result<Vec<Report>> {
     Ok(get_level_lists()?
         .iter()
-        .map(|level_list| Report::new(level_list))
+        .map(|level_list| Report::new(level_list, policy))
         .collect())
 }
 
@@ -42,65 +100,216 @@ fn get_level_lists() -> io::Result<Vec<Vec<i64>>> {
         .collect())
 }
 
-fn generate_report_safety(levels: &[i64]) -> ReportSafety {
-    let mut diffs = vec![0; levels.len() - 1];
+/// The rules a report has to follow to count as safe
+#[derive(Debug, Copy, Clone)]
+struct SafetyPolicy {
+    /// Smallest allowed difference between adjacent levels
+    min_step: i64,
+    /// Largest allowed difference between adjacent levels
+    max_step: i64,
+    /// Whether the levels all have to be increasing or all decreasing
+    require_monotonic: bool,
+    /// How many levels the Problem Dampener may remove
+    max_removals: usize,
+}
 
-    for i in 1..levels.len() {
-        diffs[i - 1] = levels[i] - levels[i - 1];
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        Self {
+            min_step: 1,
+            max_step: 3,
+            require_monotonic: true,
+            max_removals: 1,
+        }
     }
+}
 
-    if are_diffs_valid(&diffs) {
-        ReportSafety::Safe
-    } else {
-        for i in 0..levels.len() {
-            let levels = [&levels[..i], &levels[(i + 1)..]].concat();
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Trend {
+    Increasing,
+    Decreasing,
+    Either,
+}
 
-            let mut diffs = vec![0; levels.len() - 1];
+impl SafetyPolicy {
+    fn allows_step(&self, diff: i64, trend: Trend) -> bool {
+        let in_bounds = (self.min_step..=self.max_step).contains(&diff.abs());
 
-            for i in 1..levels.len() {
-                diffs[i - 1] = levels[i] - levels[i - 1];
+        match trend {
+            Trend::Increasing => in_bounds && diff >= 0,
+            Trend::Decreasing => in_bounds && diff <= 0,
+            Trend::Either => in_bounds,
+        }
+    }
+
+    fn trends(&self) -> &'static [Trend] {
+        if self.require_monotonic {
+            &[Trend::Increasing, Trend::Decreasing]
+        } else {
+            &[Trend::Either]
+        }
+    }
+
+    /// Decides in linear time whether the levels need no removals, one removal, or more (None),
+    /// returning the removed level's index when one is needed.
+    ///
+    /// For each trend it finds the longest valid prefix and suffix, removing level i then works
+    /// when the prefix reaches i, the suffix starts by i + 1, and its neighbours form a valid step
+    fn removals_up_to_one(&self, levels: &[i64]) -> Option<Vec<usize>> {
+        let len = levels.len();
+        let mut removed = None;
+
+        for trend in self.trends() {
+            let is_valid_step =
+                |from: usize, to: usize| self.allows_step(levels[to] - levels[from], *trend);
+
+            // levels[..prefix_len] and levels[suffix_start..] follow the policy
+            let mut prefix_len = len.min(1);
+            while prefix_len < len && is_valid_step(prefix_len - 1, prefix_len) {
+                prefix_len += 1;
             }
 
-            if are_diffs_valid(&diffs) {
-                return ReportSafety::SafeWithDampen;
+            if prefix_len == len {
+                return Some(vec![]);
+            }
+
+            let mut suffix_start = len - 1;
+            while suffix_start > 0 && is_valid_step(suffix_start - 1, suffix_start) {
+                suffix_start -= 1;
+            }
+
+            let can_remove = |i: &usize| {
+                *i <= prefix_len
+                    && *i + 1 >= suffix_start
+                    && (*i == 0 || *i == len - 1 || is_valid_step(*i - 1, *i + 1))
+            };
+
+            if removed.is_none() {
+                removed = (0..len).find(can_remove).map(|i| vec![i]);
             }
         }
 
-        ReportSafety::Unsafe
+        removed
     }
-}
 
-fn are_diffs_valid(diffs: &[i64]) -> bool {
-    let mut unchanged_count = 0;
-    let mut pos_count = 0;
-    let mut neg_count = 0;
-    let mut out_of_bounds_count = 0;
+    /// Finds the fewest levels that need removing for the rest to follow the policy, via the
+    /// longest subsequence where every pair of neighbouring levels is an allowed step, returning
+    /// the indices of the removed levels
+    fn removed_levels(&self, levels: &[i64]) -> Vec<usize> {
+        let mut longest_chain = vec![];
 
-    for diff in diffs {
-        match *diff {
-            0 => {
-                unchanged_count += 1;
-            }
-            1.. => {
-                pos_count += 1;
+        for trend in self.trends() {
+            // chain_lens[i] is the longest valid subsequence ending at level i, which continues
+            // on from the level at chain_prevs[i]
+            let mut chain_lens = vec![1; levels.len()];
+            let mut chain_prevs = vec![None; levels.len()];
+
+            for i in 0..levels.len() {
+                for j in 0..i {
+                    if self.allows_step(levels[i] - levels[j], *trend)
+                        && chain_lens[j] + 1 > chain_lens[i]
+                    {
+                        chain_lens[i] = chain_lens[j] + 1;
+                        chain_prevs[i] = Some(j);
+                    }
+                }
             }
-            ..0 => {
-                neg_count += 1;
+
+            let chain_end = (0..levels.len()).max_by_key(|i| (chain_lens[*i], Reverse(*i)));
+
+            if let Some(chain_end) = chain_end {
+                if chain_lens[chain_end] > longest_chain.len() {
+                    longest_chain = vec![chain_end];
+                    while let Some(prev) = chain_prevs[*longest_chain.last().unwrap()] {
+                        longest_chain.push(prev);
+                    }
+                }
             }
-        };
+        }
+
+        (0..levels.len())
+            .filter(|i| !longest_chain.contains(i))
+            .collect()
+    }
+
+    /// Lists every adjacent pair of levels that breaks the policy. When the levels must be
+    /// monotonic, steps against the report's majority trend count as reversals
+    fn violations(&self, levels: &[i64]) -> Vec<Violation> {
+        let diffs = levels.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
 
-        match diff.abs() {
-            1..4 => (),
-            _ => out_of_bounds_count += 1,
+        let increasing_count = diffs.iter().filter(|d| **d > 0).count();
+        let decreasing_count = diffs.iter().filter(|d| **d < 0).count();
+        let trend = match increasing_count.cmp(&decreasing_count) {
+            _ if !self.require_monotonic => Trend::Either,
+            Ordering::Greater => Trend::Increasing,
+            Ordering::Less => Trend::Decreasing,
+            Ordering::Equal => match diffs.iter().find(|d| **d != 0) {
+                Some(d) if *d > 0 => Trend::Increasing,
+                Some(_) => Trend::Decreasing,
+                None => Trend::Either,
+            },
         };
+
+        diffs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, diff)| {
+                let kind = match diff.abs() {
+                    0 if self.min_step > 0 => ViolationKind::ZeroStep,
+                    step if step < self.min_step => ViolationKind::StepTooSmall,
+                    step if step > self.max_step => ViolationKind::StepTooLarge,
+                    _ if !self.allows_step(*diff, trend) => ViolationKind::TrendReversal,
+                    _ => return None,
+                };
+
+                Some(Violation {
+                    index: i,
+                    levels: (levels[i], levels[i + 1]),
+                    kind,
+                })
+            })
+            .collect()
     }
+}
 
-    let different_trends = pos_count > 0 && neg_count > 0;
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ViolationKind {
+    ZeroStep,
+    StepTooSmall,
+    StepTooLarge,
+    TrendReversal,
+}
 
-    !(out_of_bounds_count + unchanged_count > 0 || different_trends)
+/// A pair of adjacent levels, starting at `index`, that breaks the safety policy
+#[derive(Debug, Copy, Clone)]
+struct Violation {
+    index: usize,
+    levels: (i64, i64),
+    kind: ViolationKind,
 }
 
-#[derive(Eq, PartialEq)]
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            ViolationKind::ZeroStep => "zero step",
+            ViolationKind::StepTooSmall => "step too small",
+            ViolationKind::StepTooLarge => "step too large",
+            ViolationKind::TrendReversal => "trend reversal",
+        };
+
+        write!(
+            f,
+            "levels {}-{} ({} -> {}): {}",
+            self.index,
+            self.index + 1,
+            self.levels.0,
+            self.levels.1,
+            kind
+        )
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
 enum ReportSafety {
     Safe,
     SafeWithDampen,
@@ -109,19 +318,76 @@ enum ReportSafety {
 
 struct Report {
     levels: Vec<i64>,
+    policy: SafetyPolicy,
+    violations: Vec<Violation>,
+    /// Indices of the levels the dampener removed, only known up front when the report is safe
+    /// or a single removal was enough
+    removed: Option<Vec<usize>>,
     safety: ReportSafety,
 }
 
 impl Report {
-    pub fn new(levels: &[i64]) -> Self {
-        let safety = generate_report_safety(levels);
+    pub fn new(levels: &[i64], policy: &SafetyPolicy) -> Self {
+        // The usual dampener only removes one level, which doesn't need the full DP
+        let removed = if policy.max_removals <= 1 {
+            policy.removals_up_to_one(levels)
+        } else {
+            Some(policy.removed_levels(levels))
+        };
+
+        let safety = match removed.as_ref().map(Vec::len) {
+            Some(0) => ReportSafety::Safe,
+            Some(n) if n <= policy.max_removals => ReportSafety::SafeWithDampen,
+            _ => ReportSafety::Unsafe,
+        };
 
         Self {
             levels: levels.to_vec(),
+            policy: *policy,
+            violations: policy.violations(levels),
+            removed,
             safety,
         }
     }
 
+    /// The fewest levels that have to be removed for the report to be safe
+    fn removals(&self) -> usize {
+        match &self.removed {
+            Some(removed) => removed.len(),
+            None => self.policy.removed_levels(&self.levels).len(),
+        }
+    }
+
+    /// Describes the classification, one line for the report then one per violation
+    fn explain(&self) -> String {
+        let levels = self
+            .levels
+            .iter()
+            .map(i64::to_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut out = match self.safety {
+            ReportSafety::Safe => format!("{}: safe", levels),
+            ReportSafety::SafeWithDampen => format!("{}: safe with dampener", levels),
+            ReportSafety::Unsafe => {
+                format!("{}: unsafe, needs {} removals", levels, self.removals())
+            }
+        };
+
+        for violation in &self.violations {
+            out.push_str(&format!("\n    {}", violation));
+        }
+
+        if let (ReportSafety::SafeWithDampen, Some(removed)) = (&self.safety, &self.removed) {
+            for i in removed {
+                out.push_str(&format!("\n    removed level {} ({})", i, self.levels[*i]));
+            }
+        }
+
+        out
+    }
+
     fn is_safe(&self) -> bool {
         matches!(self.safety, ReportSafety::Safe)
     }
@@ -130,3 +396,181 @@ impl Report {
         !matches!(self.safety, ReportSafety::Unsafe)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every report of up to `max_len` levels drawn from `0..level_count`
+    fn all_reports(max_len: u32, level_count: i64) -> Vec<Vec<i64>> {
+        let mut reports = vec![vec![]];
+
+        for len in 1..=max_len {
+            for mut n in 0..level_count.pow(len) {
+                let mut levels = vec![];
+                for _ in 0..len {
+                    levels.push(n % level_count);
+                    n /= level_count;
+                }
+                reports.push(levels);
+            }
+        }
+
+        reports
+    }
+
+    /// The original brute force: rescan the report, then rescan it again with each level removed
+    fn generate_report_safety(policy: &SafetyPolicy, levels: &[i64]) -> ReportSafety {
+        let diffs = levels.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+
+        if are_diffs_valid(policy, &diffs) {
+            ReportSafety::Safe
+        } else {
+            for i in 0..levels.len() {
+                let levels = [&levels[..i], &levels[(i + 1)..]].concat();
+                let diffs = levels.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+
+                if are_diffs_valid(policy, &diffs) {
+                    return ReportSafety::SafeWithDampen;
+                }
+            }
+
+            ReportSafety::Unsafe
+        }
+    }
+
+    fn are_diffs_valid(policy: &SafetyPolicy, diffs: &[i64]) -> bool {
+        let pos_count = diffs.iter().filter(|diff| **diff > 0).count();
+        let neg_count = diffs.iter().filter(|diff| **diff < 0).count();
+        let out_of_bounds_count = diffs
+            .iter()
+            .filter(|diff| !(policy.min_step..=policy.max_step).contains(&diff.abs()))
+            .count();
+
+        let different_trends = policy.require_monotonic && pos_count > 0 && neg_count > 0;
+
+        !(out_of_bounds_count > 0 || different_trends)
+    }
+
+    fn policies() -> [SafetyPolicy; 3] {
+        [
+            SafetyPolicy::default(),
+            SafetyPolicy {
+                min_step: 0,
+                max_step: 2,
+                ..SafetyPolicy::default()
+            },
+            SafetyPolicy {
+                require_monotonic: false,
+                ..SafetyPolicy::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn dampeners_match_brute_force() {
+        for policy in policies() {
+            for levels in all_reports(6, 7) {
+                let expected = generate_report_safety(&policy, &levels);
+
+                let linear = match policy.removals_up_to_one(&levels).map(|r| r.len()) {
+                    Some(0) => ReportSafety::Safe,
+                    Some(_) => ReportSafety::SafeWithDampen,
+                    None => ReportSafety::Unsafe,
+                };
+                assert_eq!(linear, expected, "linear: {:?} with {:?}", levels, policy);
+
+                let dp = match policy.removed_levels(&levels).len() {
+                    0 => ReportSafety::Safe,
+                    1 => ReportSafety::SafeWithDampen,
+                    _ => ReportSafety::Unsafe,
+                };
+                assert_eq!(dp, expected, "dp: {:?} with {:?}", levels, policy);
+            }
+        }
+    }
+
+    #[test]
+    fn linear_dampener_matches_dp() {
+        for policy in policies() {
+            for levels in all_reports(6, 7) {
+                let expected = match policy.removed_levels(&levels).len() {
+                    n if n <= 1 => Some(n),
+                    _ => None,
+                };
+
+                assert_eq!(
+                    policy.removals_up_to_one(&levels).map(|r| r.len()),
+                    expected,
+                    "{:?} with {:?}",
+                    levels,
+                    policy
+                );
+
+                if let Some(removed) = policy.removals_up_to_one(&levels) {
+                    let kept = levels
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| !removed.contains(i))
+                        .map(|(_, level)| *level)
+                        .collect::<Vec<_>>();
+
+                    assert!(
+                        policy.violations(&kept).is_empty(),
+                        "removing {:?} from {:?} with {:?}",
+                        removed,
+                        levels,
+                        policy
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn violations_and_explain() {
+        let policy = SafetyPolicy::default();
+        let violations = |levels: &[i64]| {
+            policy
+                .violations(levels)
+                .iter()
+                .map(|v| (v.index, v.levels, v.kind))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(violations(&[7, 6, 4, 2, 1]), []);
+        assert_eq!(
+            violations(&[1, 2, 7, 8, 9]),
+            [(1, (2, 7), ViolationKind::StepTooLarge)]
+        );
+        assert_eq!(
+            violations(&[8, 6, 4, 4, 1]),
+            [(2, (4, 4), ViolationKind::ZeroStep)]
+        );
+        assert_eq!(
+            violations(&[1, 3, 2, 4, 5]),
+            [(1, (3, 2), ViolationKind::TrendReversal)]
+        );
+
+        let policy = SafetyPolicy {
+            min_step: 2,
+            ..SafetyPolicy::default()
+        };
+        assert_eq!(
+            policy
+                .violations(&[1, 3, 4, 6])
+                .iter()
+                .map(|v| (v.index, v.kind))
+                .collect::<Vec<_>>(),
+            [(1, ViolationKind::StepTooSmall)]
+        );
+
+        let report = Report::new(&[1, 3, 2, 4, 5], &SafetyPolicy::default());
+        assert_eq!(
+            report.explain(),
+            "1 3 2 4 5: safe with dampener
+    levels 1-2 (3 -> 2): trend reversal
+    removed level 1 (3)"
+        );
+    }
+}
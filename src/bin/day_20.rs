@@ -1,17 +1,60 @@
 use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashMap, HashSet};
-use std::fs;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::{env, fs};
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
     let input = fs::read_to_string("Input_Day_20").unwrap();
     let mut maze = Maze::new(&input);
 
+    if let Some("report") = args.first().map(String::as_str) {
+        let path = args
+            .get(1)
+            .map(String::as_str)
+            .unwrap_or("Report_Day_20.md");
+        fs::write(path, report(&mut maze)).unwrap();
+        return;
+    }
+
     let cheat_score_part_1 = maze.cheat(2, 100).len();
     println!("Valid 2 picosecond cheats: {}", cheat_score_part_1);
     let cheat_score_part_2 = maze.cheat(20, 100).len();
     println!("Valid 20 picosecond cheats: {}", cheat_score_part_2);
 }
 
+type Cheat = ((usize, usize), (usize, usize));
+
+/// Builds a markdown report with a histogram of the time saved by every valid cheat
+fn report(maze: &mut Maze) -> String {
+    let mut out = String::from("# Day 20: Race Condition\n\n");
+
+    for time in [2, 20] {
+        let cheats = maze.cheat(time, 100);
+
+        let mut histogram = BTreeMap::new();
+        for saved in cheats.values() {
+            *histogram.entry(*saved).or_insert(0) += 1;
+        }
+
+        out.push_str(&format!("## {} Picosecond Cheats\n\n", time));
+        out.push_str("| Picoseconds Saved | Cheats |\n");
+        out.push_str("|---:|---:|\n");
+
+        for (saved, count) in histogram {
+            out.push_str(&format!("| {} | {} |\n", saved, count));
+        }
+
+        out.push_str(&format!(
+            "\nValid {} picosecond cheats: {}\n\n",
+            time,
+            cheats.len()
+        ));
+    }
+
+    out
+}
+
 struct Maze {
     grid: Box<[[char; 141]]>,
     start: (usize, usize),
@@ -108,14 +151,15 @@ impl Maze {
         (distances, path)
     }
 
-    fn cheat(&mut self, time: usize, threshold: u64) -> HashSet<((usize, usize), (usize, usize))> {
-        // Returns a set of all valid cheat positions, indicated by start and end position
+    fn cheat(&mut self, time: usize, threshold: u64) -> HashMap<Cheat, u64> {
+        // Returns all valid cheat positions, indicated by start and end position, along with
+        // the picoseconds they save
         let (distances, mut path) = self.dijkstra();
 
         // Need to pop from start to end
         path.reverse();
 
-        let mut cheats = HashSet::new();
+        let mut cheats = HashMap::new();
 
         let mut current_distance = 0;
         let normal_best = distances[&self.start];
@@ -123,12 +167,14 @@ impl Maze {
             current_distance += 1;
             path.iter()
                 .filter(|cell| get_distance(pos, **cell) <= time as u64)
-                .filter(|cell| {
-                    current_distance + distances[cell] + get_distance(pos, **cell) - 1
-                        <= normal_best - threshold
+                .map(|cell| {
+                    let cheat_distance =
+                        current_distance + distances[cell] + get_distance(pos, *cell) - 1;
+                    (cell, cheat_distance)
                 })
-                .for_each(|end| {
-                    cheats.insert((pos, *end));
+                .filter(|(_, cheat_distance)| *cheat_distance <= normal_best - threshold)
+                .for_each(|(end, cheat_distance)| {
+                    cheats.insert((pos, *end), normal_best - cheat_distance);
                 });
         }
 
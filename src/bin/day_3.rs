@@ -1,52 +1,658 @@
-use std::fs;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::ops::RangeInclusive;
+use std::{env, fs, io};
+
+/// How many bytes the streaming lexer reads from the input at a time
+const CHUNK_SIZE: usize = 64 * 1024;
 
 fn main() {
-    let memory = fs::read_to_string("Input_Day_3").unwrap();
-    let sum = execute_muls(&memory);
+    let mut instruction_set = InstructionSet::standard();
+    let mut in_memory = false;
+    let mut trace = false;
+    let mut path = "Input_Day_3".to_string();
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--extended" => instruction_set = InstructionSet::extended(),
+            "--in-memory" => in_memory = true,
+            "--trace" => trace = true,
+            _ => path = arg,
+        }
+    }
+
+    let memory;
+    let lexemes: Box<dyn Iterator<Item = Lexeme>> = if in_memory {
+        memory = fs::read_to_string(&path).unwrap();
+        Box::new(Lexer::new(&memory, &instruction_set))
+    } else {
+        let reader: Box<dyn BufRead> = if path == "-" {
+            Box::new(io::stdin().lock())
+        } else {
+            Box::new(BufReader::new(File::open(&path).unwrap()))
+        };
+
+        Box::new(StreamLexer::new(reader, &instruction_set, CHUNK_SIZE).map(Result::unwrap))
+    };
+
+    let (sum, conditional_sum) = if trace {
+        print_trace(&instruction_set, lexemes)
+    } else {
+        execute(&instruction_set, lexemes.filter_map(Lexeme::into_token))
+    };
 
     println!("Sum: {}", sum);
+    println!("Conditional Sum: {}", conditional_sum);
+}
+
+/// What running an instruction does to the interpreter
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Effect {
+    /// Adds the value to the accumulator
+    Accumulate(i64),
+    /// Sets the accumulator back to zero. While disabled only the unconditional total is reset,
+    /// the conditional total is kept since the reset itself isn't applied to it
+    Reset,
+    Enable,
+    Disable,
+}
+
+/// Describes an instruction's syntax, `name(op,op,...)`, and what it does when run
+#[derive(Debug, Clone)]
+struct InstructionDef {
+    name: String,
+    arity: usize,
+    /// How many digits each operand may have
+    digits: RangeInclusive<usize>,
+    effect: fn(&[u64]) -> Effect,
+}
+
+impl InstructionDef {
+    pub fn new(
+        name: &str,
+        arity: usize,
+        digits: RangeInclusive<usize>,
+        effect: fn(&[u64]) -> Effect,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            digits,
+            effect,
+        }
+    }
+}
+
+/// The instructions the lexer recognises, new ones can be added with `register`
+#[derive(Debug, Clone, Default)]
+struct InstructionSet {
+    defs: Vec<InstructionDef>,
+}
+
+impl InstructionSet {
+    /// `mul(X,Y)`, `do()` and `don't()` from the puzzle
+    pub fn standard() -> Self {
+        let mut set = Self::default();
 
-    let conditional_sum: u64 = memory
-        .split("do()")
-        .map(|s| {
-            if let Some(index) = s.find("don't()") {
-                s[..index].to_string()
-            } else {
-                s.to_string()
+        set.register(InstructionDef::new("mul", 2, 1..=3, |ops| {
+            Effect::Accumulate((ops[0] * ops[1]) as i64)
+        }));
+        set.register(InstructionDef::new("do", 0, 0..=0, |_| Effect::Enable));
+        set.register(InstructionDef::new("don't", 0, 0..=0, |_| Effect::Disable));
+
+        set
+    }
+
+    /// The standard instructions plus `add(X,Y)`, `neg(X)` and `reset()`
+    pub fn extended() -> Self {
+        let mut set = Self::standard();
+
+        set.register(InstructionDef::new("add", 2, 1..=3, |ops| {
+            Effect::Accumulate((ops[0] + ops[1]) as i64)
+        }));
+        set.register(InstructionDef::new("neg", 1, 1..=3, |ops| {
+            Effect::Accumulate(-(ops[0] as i64))
+        }));
+        set.register(InstructionDef::new("reset", 0, 0..=0, |_| Effect::Reset));
+
+        set
+    }
+
+    pub fn register(&mut self, def: InstructionDef) {
+        self.defs.push(def);
+    }
+
+    pub fn get(&self, def_index: usize) -> &InstructionDef {
+        &self.defs[def_index]
+    }
+
+    /// Length in bytes of the longest possible instruction in the set
+    pub fn max_instruction_len(&self) -> usize {
+        self.defs
+            .iter()
+            .map(|def| {
+                let separator_count = def.arity.saturating_sub(1);
+                def.name.len() + 2 + def.arity * def.digits.end() + separator_count
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Tries to read an instruction from the start of the bytes at the given offset in memory,
+    /// returning it along with how many bytes to skip past it.
+    ///
+    /// When an instruction's name is there but the rest of the call is malformed, the furthest
+    /// any definition got is returned as a near miss, which only skips the one byte
+    fn read_lexeme(&self, bytes: &[u8], offset: usize) -> Option<(Lexeme, usize)> {
+        let mut near_miss: Option<NearMiss> = None;
+
+        for (def_index, def) in self.defs.iter().enumerate() {
+            if !bytes.starts_with(def.name.as_bytes()) {
+                continue;
             }
-        })
-        .map(|s| execute_muls(&s))
-        .sum();
 
-    println!("Conditional Sum: {}", conditional_sum);
+            match read_call(bytes, def) {
+                Ok((operands, len)) => {
+                    let token = Token {
+                        offset,
+                        def_index,
+                        operands,
+                    };
+
+                    return Some((Lexeme::Instruction(token), len));
+                }
+                Err((rejection, len)) => {
+                    if near_miss.as_ref().is_none_or(|miss| miss.text.len() < len) {
+                        near_miss = Some(NearMiss {
+                            offset,
+                            def_index,
+                            text: String::from_utf8_lossy(&bytes[..len]).to_string(),
+                            rejection,
+                        });
+                    }
+                }
+            }
+        }
+
+        near_miss.map(|miss| (Lexeme::NearMiss(miss), 1))
+    }
+}
+
+/// An instruction recognised in the corrupted memory, along with the byte offset it starts at
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Token {
+    offset: usize,
+    /// Index of the instruction's definition in the instruction set
+    def_index: usize,
+    operands: Vec<u64>,
 }
 
-fn execute_muls(input: &str) -> u64 {
-    input
-        .split("mul")
-        .filter(|s| s.starts_with('(') && s.contains(')'))
-        .map(|s| {
-            let first_end_paren_index = s.find(')').unwrap();
-            s[..=first_end_paren_index].to_string()
-        })
-        .filter(|s| s.len() <= 9 && s.contains(','))
-        .map(|s| s.trim_start_matches('(').trim_end_matches(')').to_string())
-        .map(|s| {
-            let comma_index = s.find(',').unwrap();
-            (
-                s[..comma_index].to_string(),
-                s[comma_index + 1..].to_string(),
+/// Why a call to a known instruction was rejected
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Rejection {
+    /// Found some other byte, or the end of memory, where the call needed a `(`, `,` or `)`
+    UnexpectedByte { expected: u8, found: Option<u8> },
+    /// The 1-based operand doesn't have an allowed number of digits
+    BadOperand {
+        operand: usize,
+        digits: RangeInclusive<usize>,
+    },
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::UnexpectedByte {
+                expected,
+                found: Some(found),
+            } => write!(
+                f,
+                "expected '{}' but found '{}'",
+                *expected as char,
+                found.escape_ascii()
+            ),
+            Rejection::UnexpectedByte {
+                expected,
+                found: None,
+            } => write!(f, "expected '{}' but memory ended", *expected as char),
+            Rejection::BadOperand { operand, digits } => write!(
+                f,
+                "operand {} needs {} to {} digits",
+                operand,
+                digits.start(),
+                digits.end()
+            ),
+        }
+    }
+}
+
+/// Something that starts like an instruction but isn't one, e.g. `mul(1234,5)` or `mul[3,7]`
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct NearMiss {
+    offset: usize,
+    def_index: usize,
+    /// The memory from the instruction's name up to and including where it went wrong
+    text: String,
+    rejection: Rejection,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Lexeme {
+    Instruction(Token),
+    NearMiss(NearMiss),
+}
+
+impl Lexeme {
+    pub fn into_token(self) -> Option<Token> {
+        match self {
+            Lexeme::Instruction(token) => Some(token),
+            Lexeme::NearMiss(_) => None,
+        }
+    }
+}
+
+/// Scans the memory once, yielding every well formed instruction from the instruction set along
+/// with the near misses
+struct Lexer<'a> {
+    memory: &'a [u8],
+    instruction_set: &'a InstructionSet,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(memory: &'a str, instruction_set: &'a InstructionSet) -> Self {
+        Self {
+            memory: memory.as_bytes(),
+            instruction_set,
+            pos: 0,
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Lexeme;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.memory.len() {
+            let offset = self.pos;
+
+            match self
+                .instruction_set
+                .read_lexeme(&self.memory[offset..], offset)
+            {
+                Some((lexeme, len)) => {
+                    self.pos += len;
+                    return Some(lexeme);
+                }
+                None => self.pos += 1,
+            }
+        }
+
+        None
+    }
+}
+
+/// Lexes memory from a reader in fixed size chunks, so only a chunk plus one instruction's
+/// worth of bytes is held at once. Yields the same tokens as `Lexer` would on the whole input
+struct StreamLexer<'a, R: BufRead> {
+    reader: R,
+    instruction_set: &'a InstructionSet,
+    chunk_size: usize,
+    /// Bytes needed past a position before an instruction can be read from it, so
+    /// instructions straddling two chunks are never cut short
+    lookahead: usize,
+    buffer: Vec<u8>,
+    /// Offset of `buffer[0]` in the whole input
+    buffer_offset: usize,
+    pos: usize,
+    is_eof: bool,
+}
+
+impl<'a, R: BufRead> StreamLexer<'a, R> {
+    pub fn new(reader: R, instruction_set: &'a InstructionSet, chunk_size: usize) -> Self {
+        Self {
+            reader,
+            instruction_set,
+            chunk_size: chunk_size.max(1),
+            lookahead: instruction_set.max_instruction_len() + 1,
+            buffer: vec![],
+            buffer_offset: 0,
+            pos: 0,
+            is_eof: false,
+        }
+    }
+
+    /// Drops the bytes already scanned and reads the next chunk onto the end of the buffer
+    fn read_chunk(&mut self) -> io::Result<()> {
+        self.buffer.drain(..self.pos);
+        self.buffer_offset += self.pos;
+        self.pos = 0;
+
+        let mut read_count = 0;
+        while read_count < self.chunk_size {
+            let data = self.reader.fill_buf()?;
+
+            if data.is_empty() {
+                self.is_eof = true;
+                break;
+            }
+
+            let len = data.len().min(self.chunk_size - read_count);
+            self.buffer.extend_from_slice(&data[..len]);
+            self.reader.consume(len);
+            read_count += len;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for StreamLexer<'_, R> {
+    type Item = io::Result<Lexeme>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while !self.is_eof && self.buffer.len() - self.pos < self.lookahead {
+                if let Err(err) = self.read_chunk() {
+                    return Some(Err(err));
+                }
+            }
+
+            if self.pos >= self.buffer.len() {
+                return None;
+            }
+
+            let offset = self.pos;
+
+            match self
+                .instruction_set
+                .read_lexeme(&self.buffer[offset..], self.buffer_offset + offset)
+            {
+                Some((lexeme, len)) => {
+                    self.pos += len;
+                    return Some(Ok(lexeme));
+                }
+                None => self.pos += 1,
+            }
+        }
+    }
+}
+
+/// Reads `name(op,op,...)` for the given definition from bytes starting with its name,
+/// returning the operands and the call's length in bytes, or why it was rejected and how many
+/// bytes were read up to and including the problem
+fn read_call(bytes: &[u8], def: &InstructionDef) -> Result<(Vec<u64>, usize), (Rejection, usize)> {
+    let expect = |len: usize, expected: u8| match bytes.get(len) {
+        Some(&found) if found == expected => Ok(len + 1),
+        found => Err((
+            Rejection::UnexpectedByte {
+                expected,
+                found: found.copied(),
+            },
+            bytes.len().min(len + 1),
+        )),
+    };
+
+    let mut len = expect(def.name.len(), b'(')?;
+    let mut operands = Vec::with_capacity(def.arity);
+
+    for i in 0..def.arity {
+        if i > 0 {
+            len = expect(len, b',')?;
+        }
+
+        match read_operand(&bytes[len..], &def.digits) {
+            Ok((operand, operand_len)) => {
+                operands.push(operand);
+                len += operand_len;
+            }
+            Err(operand_len) => {
+                let rejection = Rejection::BadOperand {
+                    operand: i + 1,
+                    digits: def.digits.clone(),
+                };
+
+                return Err((rejection, len + operand_len));
+            }
+        }
+    }
+
+    len = expect(len, b')')?;
+
+    Ok((operands, len))
+}
+
+/// Reads an operand with an allowed number of digits, returning its value and length in bytes,
+/// or how many digits were read before it was rejected.
+///
+/// Digits past one more than the maximum aren't looked at, so the streaming lexer never needs
+/// more than one instruction's worth of lookahead
+fn read_operand(bytes: &[u8], digits: &RangeInclusive<usize>) -> Result<(u64, usize), usize> {
+    let len = bytes
+        .iter()
+        .take(digits.end() + 1)
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+
+    if !digits.contains(&len) {
+        return Err(len);
+    }
+
+    let value = bytes[..len]
+        .iter()
+        .fold(0, |acc, b| acc * 10 + (b - b'0') as u64);
+
+    Ok((value, len))
+}
+
+/// Keeps the accumulator with every instruction applied and the accumulator with only those
+/// applied while enabled
+struct Interpreter {
+    sum: i64,
+    conditional_sum: i64,
+    is_enabled: bool,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            sum: 0,
+            conditional_sum: 0,
+            is_enabled: true,
+        }
+    }
+
+    pub fn run(&mut self, instruction_set: &InstructionSet, token: &Token) -> Effect {
+        let def = instruction_set.get(token.def_index);
+        let effect = (def.effect)(&token.operands);
+
+        match effect {
+            Effect::Accumulate(value) => {
+                self.sum += value;
+
+                if self.is_enabled {
+                    self.conditional_sum += value;
+                }
+            }
+            Effect::Reset => {
+                self.sum = 0;
+
+                if self.is_enabled {
+                    self.conditional_sum = 0;
+                }
+            }
+            Effect::Enable => self.is_enabled = true,
+            Effect::Disable => self.is_enabled = false,
+        }
+
+        effect
+    }
+}
+
+/// Runs the tokens, returning the sum and the conditional sum
+fn execute(instruction_set: &InstructionSet, tokens: impl Iterator<Item = Token>) -> (i64, i64) {
+    let mut interpreter = Interpreter::new();
+
+    for token in tokens {
+        interpreter.run(instruction_set, &token);
+    }
+
+    (interpreter.sum, interpreter.conditional_sum)
+}
+
+/// Runs the lexemes like `execute`, printing a line for every instruction with whether it was
+/// enabled and the value it accumulated, and for every near miss with why it was rejected
+fn print_trace(
+    instruction_set: &InstructionSet,
+    lexemes: impl Iterator<Item = Lexeme>,
+) -> (i64, i64) {
+    let mut interpreter = Interpreter::new();
+
+    println!(
+        "{:>10}  {:<20}  {:<8}  Value",
+        "Offset", "Instruction", "Enabled"
+    );
+
+    for lexeme in lexemes {
+        match lexeme {
+            Lexeme::Instruction(token) => {
+                let def = instruction_set.get(token.def_index);
+                let was_enabled = interpreter.is_enabled;
+
+                let call = format!(
+                    "{}({})",
+                    def.name,
+                    token
+                        .operands
+                        .iter()
+                        .map(|operand| operand.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                );
+
+                let value = match interpreter.run(instruction_set, &token) {
+                    Effect::Accumulate(value) => value.to_string(),
+                    _ => "-".to_string(),
+                };
+
+                println!(
+                    "{:>10}  {:<20}  {:<8}  {}",
+                    token.offset,
+                    call,
+                    if was_enabled { "yes" } else { "no" },
+                    value
+                );
+            }
+            Lexeme::NearMiss(miss) => println!(
+                "{:>10}  {:<20}  rejected: {}",
+                miss.offset, miss.text, miss.rejection
+            ),
+        }
+    }
+
+    println!();
+
+    (interpreter.sum, interpreter.conditional_sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMORY: &str =
+        "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))\
+        mul(123,456)mul(1234,5)do()add(12,3)neg(7)reset()mul(999,999)don't()mul(1,";
+
+    #[test]
+    fn stream_lexer_matches_lexer() {
+        for instruction_set in [InstructionSet::standard(), InstructionSet::extended()] {
+            let expected = Lexer::new(MEMORY, &instruction_set).collect::<Vec<_>>();
+
+            for chunk_size in 1..=MEMORY.len() + 1 {
+                let lexemes = StreamLexer::new(MEMORY.as_bytes(), &instruction_set, chunk_size)
+                    .collect::<io::Result<Vec<_>>>()
+                    .unwrap();
+
+                assert_eq!(lexemes, expected, "chunk size {}", chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn near_misses() {
+        let instruction_set = InstructionSet::standard();
+        let memory = "mul(1234,5)mul[3,7]mul ( 2,4)mul(2,4";
+
+        let near_misses = Lexer::new(memory, &instruction_set)
+            .filter_map(|lexeme| match lexeme {
+                Lexeme::NearMiss(miss) => Some(format!("{}: {}", miss.text, miss.rejection)),
+                Lexeme::Instruction(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            near_misses,
+            [
+                "mul(1234: operand 1 needs 1 to 3 digits",
+                "mul[: expected '(' but found '['",
+                "mul : expected '(' but found ' '",
+                "mul(2,4: expected ')' but memory ended",
+            ]
+        );
+    }
+
+    #[test]
+    fn standard_examples() {
+        let instruction_set = InstructionSet::standard();
+        let run = |memory| {
+            execute(
+                &instruction_set,
+                Lexer::new(memory, &instruction_set).filter_map(Lexeme::into_token),
             )
-        })
-        .flat_map(|(lhs, rhs)| {
-            let lhs = lhs.parse::<u16>().ok();
-            let rhs = rhs.parse::<u16>().ok();
-
-            match (lhs, rhs) {
-                (Some(lhs), Some(rhs)) => Some((lhs, rhs)),
-                _ => None,
-            }
-        })
-        .map(|(lhs, rhs)| lhs as u64 * rhs as u64)
-        .sum()
+        };
+
+        let (sum, _) =
+            run("xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))");
+        let (_, conditional_sum) =
+            run("xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))");
+
+        assert_eq!((sum, conditional_sum), (161, 48));
+    }
+
+    #[test]
+    fn extended_effects() {
+        let instruction_set = InstructionSet::extended();
+        let run = |memory: &str| {
+            execute(
+                &instruction_set,
+                Lexer::new(memory, &instruction_set).filter_map(Lexeme::into_token),
+            )
+        };
+
+        // Each instruction runs after mul(2,5) has brought both totals to 10
+        let cases = [
+            ("mul(3,4)", (22, 22), (22, 10)),
+            ("add(3,4)", (17, 17), (17, 10)),
+            ("neg(3)", (7, 7), (7, 10)),
+            ("reset()", (0, 0), (0, 10)),
+            ("do()mul(1,1)", (11, 11), (11, 11)),
+            ("don't()mul(1,1)", (11, 10), (11, 10)),
+        ];
+
+        for (instruction, enabled, disabled) in cases {
+            assert_eq!(
+                run(&format!("mul(2,5){}", instruction)),
+                enabled,
+                "{} enabled",
+                instruction
+            );
+            assert_eq!(
+                run(&format!("mul(2,5)don't(){}", instruction)),
+                disabled,
+                "{} disabled",
+                instruction
+            );
+        }
+    }
 }
@@ -1,43 +1,376 @@
-use std::fs;
+use std::collections::{HashMap, VecDeque};
+use std::{env, fs, process};
+
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_DIM: &str = "\x1b[2m";
+
+/// Random spots tried for each word before the generator starts the grid over
+const PLACEMENT_ATTEMPTS: usize = 1000;
+const GENERATOR_ATTEMPTS: usize = 100;
 
 fn main() {
+    if env::args().nth(1).as_deref() == Some("generate") {
+        generate_main(env::args().skip(2).collect());
+        return;
+    }
+
+    let mut word = Wordsearch::WORD.to_string();
+    let mut dictionary_path = None;
+    let mut template = None;
+    let mut render = None;
+    let mut html_path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dictionary" => dictionary_path = args.next(),
+            "--template" => template = args.next().map(|pattern| Template::new(&pattern)),
+            "--render" => render = args.next(),
+            "--html" => html_path = args.next(),
+            _ => word = arg,
+        }
+    }
+
     let input = fs::read_to_string("Input_Day_4").unwrap();
 
     let wordsearch = Wordsearch::new(&input);
 
-    let forward_horizontal_count = wordsearch.count_horizontal();
-    println!("FW Horizontal Count: {}", forward_horizontal_count);
-    let backward_horizontal_count = wordsearch.count_horizontal_rev();
-    println!("BW Horizontal Count: {}", backward_horizontal_count);
-
-    let forward_vertical_count = wordsearch.count_vertical();
-    println!("FW Vertical Count: {}", forward_vertical_count);
-    let backward_vertical_count = wordsearch.count_vertical_rev();
-    println!("BW Vertical Count: {}", backward_vertical_count);
-
-    let forward_northwest_count = wordsearch.count_diagonal_northwest();
-    println!("FW Northwest Count: {}", forward_northwest_count);
-    let backward_northwest_count = wordsearch.count_diagonal_northwest_rev();
-    println!("BW Northwest Count: {}", backward_northwest_count);
-
-    let forward_southeast_count = wordsearch.count_diagonal_southeast();
-    println!("FW Southeast Count: {}", forward_southeast_count);
-    let backward_southeast_count = wordsearch.count_diagonal_southeast_rev();
-    println!("BW Southeast Count: {}", backward_southeast_count);
-
-    let total = forward_horizontal_count
-        + backward_horizontal_count
-        + forward_vertical_count
-        + backward_vertical_count
-        + forward_northwest_count
-        + backward_northwest_count
-        + forward_southeast_count
-        + backward_southeast_count;
-
-    println!("Total: {}", total);
-
-    let xmas_count = wordsearch.count_x_mas();
+    if let Some(path) = dictionary_path {
+        let dictionary = fs::read_to_string(path).unwrap();
+        let words = dictionary
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+
+        let matches = wordsearch.find_words(&words);
+
+        for (word, word_matches) in words.iter().zip(&matches) {
+            println!("{}: {}", word, word_matches.len());
+        }
+
+        println!("Total: {}", matches.iter().map(Vec::len).sum::<usize>());
+
+        let matches = matches.concat();
+        show_matches(
+            &wordsearch,
+            &matches,
+            render.as_deref(),
+            html_path.as_deref(),
+        );
+        return;
+    }
+
+    let matches = wordsearch.find_word(&word, &Direction::ALL);
+
+    for direction in Direction::ALL {
+        let count = matches.iter().filter(|m| m.direction == direction).count();
+        println!("{:?} Count: {}", direction, count);
+    }
+
+    println!("Total: {}", matches.len());
+
+    let xmas_count = wordsearch
+        .find_template(&Template::new(Wordsearch::X_MAS))
+        .len();
     println!("X-MAS Total: {}", xmas_count);
+
+    if let Some(template) = template {
+        let template_count = wordsearch.find_template(&template).len();
+        println!("Template Total: {}", template_count);
+    }
+
+    show_matches(
+        &wordsearch,
+        &matches,
+        render.as_deref(),
+        html_path.as_deref(),
+    );
+}
+
+fn print_generate_usage_and_exit() -> ! {
+    eprintln!("Usage: day_4 generate <width> <height> <words file> [--directions <N,NE,E,...>]");
+    eprintln!("          [--seed <seed>]");
+    process::exit(1);
+}
+
+/// Generates a wordsearch holding every word from the file exactly once, printing the grid
+/// followed by the answer key
+fn generate_main(args: Vec<String>) {
+    let mut sizes = vec![];
+    let mut words_path = None;
+    let mut directions = Direction::ALL.to_vec();
+    let mut seed = 0;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--directions" => {
+                match args
+                    .next()
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(Direction::from_compass)
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(parsed) => directions = parsed,
+                    None => print_generate_usage_and_exit(),
+                }
+            }
+            "--seed" => match args.next().map(|seed| seed.parse::<u64>()) {
+                Some(Ok(parsed)) => seed = parsed,
+                _ => print_generate_usage_and_exit(),
+            },
+            _ if sizes.len() < 2 => match arg.parse::<usize>() {
+                Ok(size) => sizes.push(size),
+                Err(_) => print_generate_usage_and_exit(),
+            },
+            _ => words_path = Some(arg),
+        }
+    }
+
+    let (&[width, height], Some(words_path)) = (sizes.as_slice(), words_path) else {
+        print_generate_usage_and_exit();
+    };
+
+    let words_data = fs::read_to_string(words_path).unwrap();
+    let words = words_data
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+
+    let Some((wordsearch, answers)) = generate(width, height, &words, &directions, seed) else {
+        eprintln!(
+            "Couldn't fit every word exactly once after {} attempts, try a bigger grid, more \
+             directions or another seed",
+            GENERATOR_ATTEMPTS
+        );
+        process::exit(1);
+    };
+
+    for row in &wordsearch.board {
+        println!("{}", row.iter().collect::<String>());
+    }
+    println!();
+
+    for (word, answer) in words.iter().zip(answers) {
+        println!(
+            "{}: ({}, {}) {:?}",
+            word, answer.start.0, answer.start.1, answer.direction
+        );
+    }
+}
+
+/// Prints the grid highlighted with `--render <ansi | plain>` and writes it out with `--html`
+fn show_matches(
+    wordsearch: &Wordsearch,
+    matches: &[Match],
+    render: Option<&str>,
+    html_path: Option<&str>,
+) {
+    match render {
+        Some("ansi") => print!("{}", wordsearch.render_ansi(matches)),
+        Some("plain") => print!("{}", wordsearch.render_plain(matches)),
+        Some(other) => eprintln!("Unknown render mode {}, try ansi or plain", other),
+        None => (),
+    }
+
+    if let Some(path) = html_path {
+        fs::write(path, wordsearch.render_html(matches)).unwrap();
+        println!("Wrote {}", path);
+    }
+}
+
+/// The direction a word reads in, where y increases going south
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum Direction {
+    East,
+    West,
+    South,
+    North,
+    SouthEast,
+    NorthWest,
+    NorthEast,
+    SouthWest,
+}
+
+impl Direction {
+    const ALL: [Direction; 8] = [
+        Direction::East,
+        Direction::West,
+        Direction::South,
+        Direction::North,
+        Direction::SouthEast,
+        Direction::NorthWest,
+        Direction::NorthEast,
+        Direction::SouthWest,
+    ];
+
+    /// The directions whose lines the dictionary search scans, every other direction is one of
+    /// these read backwards
+    const LINES: [Direction; 4] = [
+        Direction::East,
+        Direction::South,
+        Direction::SouthEast,
+        Direction::NorthEast,
+    ];
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+            Direction::South => Direction::North,
+            Direction::North => Direction::South,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::NorthWest => Direction::SouthEast,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::SouthWest => Direction::NorthEast,
+        }
+    }
+
+    /// Parses a compass abbreviation like `N` or `se`
+    pub fn from_compass(compass: &str) -> Option<Direction> {
+        match compass.trim().to_ascii_uppercase().as_str() {
+            "E" => Some(Direction::East),
+            "W" => Some(Direction::West),
+            "S" => Some(Direction::South),
+            "N" => Some(Direction::North),
+            "SE" => Some(Direction::SouthEast),
+            "NW" => Some(Direction::NorthWest),
+            "NE" => Some(Direction::NorthEast),
+            "SW" => Some(Direction::SouthWest),
+            _ => None,
+        }
+    }
+
+    /// Foreground colour code for highlighting words read in this direction
+    pub fn ansi_colour(&self) -> u8 {
+        match self {
+            Direction::East => 31,
+            Direction::West => 32,
+            Direction::South => 33,
+            Direction::North => 34,
+            Direction::SouthEast => 35,
+            Direction::NorthWest => 36,
+            Direction::NorthEast => 91,
+            Direction::SouthWest => 92,
+        }
+    }
+
+    pub fn html_colour(&self) -> &'static str {
+        match self {
+            Direction::East => "#d62728",
+            Direction::West => "#2ca02c",
+            Direction::South => "#bcbd22",
+            Direction::North => "#1f77b4",
+            Direction::SouthEast => "#9467bd",
+            Direction::NorthWest => "#17becf",
+            Direction::NorthEast => "#ff7f0e",
+            Direction::SouthWest => "#8c564b",
+        }
+    }
+
+    /// How far one step in this direction moves along x and y
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+            Direction::South => (0, 1),
+            Direction::North => (0, -1),
+            Direction::SouthEast => (1, 1),
+            Direction::NorthWest => (-1, -1),
+            Direction::NorthEast => (1, -1),
+            Direction::SouthWest => (-1, 1),
+        }
+    }
+}
+
+/// A 2D pattern of letters and `.` wildcards, e.g. `M.S/.A./M.S`, that matches in any of its
+/// rotations and reflections
+#[derive(Debug, Clone)]
+struct Template {
+    /// Each distinct rotation and reflection of the pattern, `None` cells match anything
+    variants: Vec<Vec<Vec<Option<char>>>>,
+}
+
+impl Template {
+    /// Parses the pattern's rows separated by `/`, short rows are padded with wildcards
+    pub fn new(pattern: &str) -> Self {
+        let rows = pattern.split('/').map(str::trim).collect::<Vec<_>>();
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut shape = rows
+            .iter()
+            .map(|row| {
+                let mut cells = row
+                    .chars()
+                    .map(|c| if c == '.' { None } else { Some(c) })
+                    .collect::<Vec<_>>();
+                cells.resize(width, None);
+                cells
+            })
+            .collect::<Vec<_>>();
+
+        let mut variants: Vec<Vec<Vec<Option<char>>>> = vec![];
+
+        for _ in 0..2 {
+            for _ in 0..4 {
+                if !variants.contains(&shape) {
+                    variants.push(shape.clone());
+                }
+
+                shape = Self::rotate(&shape);
+            }
+
+            shape.iter_mut().for_each(|row| row.reverse());
+        }
+
+        Self { variants }
+    }
+
+    /// Rotates the shape a quarter turn clockwise
+    fn rotate(shape: &[Vec<Option<char>>]) -> Vec<Vec<Option<char>>> {
+        let width = shape.first().map(Vec::len).unwrap_or(0);
+
+        (0..width)
+            .map(|x| shape.iter().rev().map(|row| row[x]).collect())
+            .collect()
+    }
+}
+
+/// Where a template matched, with (x, y) the top left of the matching variant
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Placement {
+    position: (usize, usize),
+    variant: usize,
+}
+
+/// A word found in the wordsearch, starting at (x, y) on its first letter
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Match {
+    start: (usize, usize),
+    direction: Direction,
+    len: usize,
+}
+
+impl Match {
+    /// Positions of the word's letters from first to last
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (x, y) = self.start;
+        let (dx, dy) = self.direction.offset();
+
+        (0..self.len as isize).map(move |i| {
+            (
+                (x as isize + dx * i) as usize,
+                (y as isize + dy * i) as usize,
+            )
+        })
+    }
 }
 
 struct Wordsearch {
@@ -46,6 +379,7 @@ struct Wordsearch {
 
 impl Wordsearch {
     const WORD: &'static str = "XMAS";
+    const X_MAS: &'static str = "M.S/.A./M.S";
     pub fn new(board_data: &str) -> Self {
         let mut board = vec![];
 
@@ -57,193 +391,756 @@ impl Wordsearch {
         Self { board }
     }
 
-    fn count_horizontal(&self) -> u64 {
-        let mut count = 0;
+    /// Blank cells in the grid are left as `.`
+    fn from_grid(grid: &[Vec<Option<char>>]) -> Self {
+        let board = grid
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.unwrap_or('.')).collect())
+            .collect();
+
+        Self { board }
+    }
+
+    fn get(&self, x: usize, y: usize) -> Option<char> {
+        self.board.get(y)?.get(x).copied()
+    }
+
+    /// Finds every place the word reads in one of the directions, a word reading in several
+    /// directions from the same letter is a match for each of them
+    fn find_word(&self, word: &str, directions: &[Direction]) -> Vec<Match> {
+        let word = word.chars().collect::<Vec<_>>();
+        let mut matches = vec![];
+
+        if word.is_empty() {
+            return matches;
+        }
 
         for y in 0..self.board.len() {
-            for x in 0..self.board[y].len() - 3 {
-                let group = &[
-                    self.board[y][x],
-                    self.board[y][x + 1],
-                    self.board[y][x + 2],
-                    self.board[y][x + 3],
-                ];
-
-                if let &['X', 'M', 'A', 'S'] = group {
-                    count += 1;
+            for x in 0..self.board[y].len() {
+                if self.board[y][x] != word[0] {
+                    continue;
+                }
+
+                for &direction in directions {
+                    if self.reads_word(&word, (x, y), direction) {
+                        matches.push(Match {
+                            start: (x, y),
+                            direction,
+                            len: word.len(),
+                        });
+                    }
                 }
             }
         }
 
-        count
+        matches
     }
 
-    fn count_horizontal_rev(&self) -> u64 {
-        let mut count = 0;
+    fn reads_word(&self, word: &[char], start: (usize, usize), direction: Direction) -> bool {
+        let (dx, dy) = direction.offset();
 
-        for y in 0..self.board.len() {
-            for x in 0..self.board[y].len() - 3 {
-                let group = &[
-                    self.board[y][x],
-                    self.board[y][x + 1],
-                    self.board[y][x + 2],
-                    self.board[y][x + 3],
-                ];
-
-                if let &['S', 'A', 'M', 'X'] = group {
-                    count += 1;
+        word.iter().enumerate().all(|(i, letter)| {
+            let x = start.0.checked_add_signed(dx * i as isize);
+            let y = start.1.checked_add_signed(dy * i as isize);
+
+            match (x, y) {
+                (Some(x), Some(y)) => self.get(x, y) == Some(*letter),
+                _ => false,
+            }
+        })
+    }
+
+    /// Finds every word from the dictionary in any direction, returning the matches for each word
+    /// in the same order as the words.
+    ///
+    /// Every row, column and diagonal is scanned once with an automaton of the words and their
+    /// reverses, so a palindrome is only matched once in each place, in the direction of the
+    /// line it was found on
+    fn find_words(&self, words: &[&str]) -> Vec<Vec<Match>> {
+        let automaton = WordAutomaton::new(words);
+        let mut matches = vec![vec![]; words.len()];
+
+        let height = self.board.len() as isize;
+        let width = self.board.iter().map(Vec::len).max().unwrap_or(0) as isize;
+
+        for direction in Direction::LINES {
+            let (dx, dy) = direction.offset();
+
+            let starts = match direction {
+                Direction::East => (0..height).map(|y| (0, y)).collect::<Vec<_>>(),
+                Direction::South => (0..width).map(|x| (x, 0)).collect(),
+                Direction::SouthEast => (0..width)
+                    .map(|x| (x, 0))
+                    .chain((1..height).map(|y| (0, y)))
+                    .collect(),
+                _ => (0..height)
+                    .map(|y| (0, y))
+                    .chain((1..width).map(|x| (x, height - 1)))
+                    .collect(),
+            };
+
+            for (x, y) in starts {
+                let mut line = vec![];
+                let (mut x, mut y) = (x, y);
+
+                while (0..width).contains(&x) && (0..height).contains(&y) {
+                    line.push((x as usize, y as usize));
+                    x += dx;
+                    y += dy;
+                }
+
+                let letters = line.iter().map(|&(x, y)| self.get(x, y));
+
+                for (end, pattern) in automaton.find(letters) {
+                    let word_len = automaton.word_lens[pattern.word_index];
+
+                    // Single letters would otherwise be found once per line through them
+                    if word_len == 1 && direction != Direction::East {
+                        continue;
+                    }
+
+                    let found = if pattern.is_reversed {
+                        Match {
+                            start: line[end],
+                            direction: direction.opposite(),
+                            len: word_len,
+                        }
+                    } else {
+                        Match {
+                            start: line[end + 1 - word_len],
+                            direction,
+                            len: word_len,
+                        }
+                    };
+
+                    for &index in &automaton.word_indices[pattern.word_index] {
+                        matches[index].push(found);
+                    }
                 }
             }
         }
 
-        count
+        matches
     }
 
-    fn count_vertical(&self) -> u64 {
-        let mut count = 0;
+    /// The direction of the first match covering each cell, or `None` for unused cells
+    fn highlights(&self, matches: &[Match]) -> Vec<Vec<Option<Direction>>> {
+        let mut highlights = self
+            .board
+            .iter()
+            .map(|row| vec![None; row.len()])
+            .collect::<Vec<_>>();
 
-        for y in 0..self.board.len() - 3 {
-            for x in 0..self.board[y].len() {
-                let group = &[
-                    self.board[y][x],
-                    self.board[y + 1][x],
-                    self.board[y + 2][x],
-                    self.board[y + 3][x],
-                ];
-
-                if let &['X', 'M', 'A', 'S'] = group {
-                    count += 1;
+        for m in matches {
+            for (x, y) in m.cells() {
+                highlights[y][x].get_or_insert(m.direction);
+            }
+        }
+
+        highlights
+    }
+
+    /// The grid with matched letters coloured by direction and the rest dimmed
+    fn render_ansi(&self, matches: &[Match]) -> String {
+        let mut out = String::new();
+
+        for (row, highlights) in self.board.iter().zip(self.highlights(matches)) {
+            for (letter, highlight) in row.iter().zip(highlights) {
+                match highlight {
+                    Some(direction) => out.push_str(&format!(
+                        "\x1b[1;{}m{}{}",
+                        direction.ansi_colour(),
+                        letter,
+                        ANSI_RESET
+                    )),
+                    None => out.push_str(&format!("{}{}{}", ANSI_DIM, letter, ANSI_RESET)),
                 }
             }
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// The grid with unused letters replaced by `.`, like the puzzle's examples
+    fn render_plain(&self, matches: &[Match]) -> String {
+        let mut out = String::new();
+
+        for (row, highlights) in self.board.iter().zip(self.highlights(matches)) {
+            for (letter, highlight) in row.iter().zip(highlights) {
+                out.push(if highlight.is_some() { *letter } else { '.' });
+            }
+            out.push('\n');
         }
 
-        count
+        out
     }
 
-    fn count_vertical_rev(&self) -> u64 {
-        let mut count = 0;
+    /// A standalone page with the same view as `render_ansi` and a legend of the directions
+    fn render_html(&self, matches: &[Match]) -> String {
+        let mut out = String::new();
 
-        for y in 0..self.board.len() - 3 {
+        out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        out.push_str("<title>Wordsearch</title>\n<style>\n");
+        out.push_str("pre { font-size: 1.2em; line-height: 1.2em; }\n");
+        out.push_str(".unused { color: #ccc; }\n");
+        for direction in Direction::ALL {
+            out.push_str(&format!(
+                ".{:?} {{ color: {}; font-weight: bold; }}\n",
+                direction,
+                direction.html_colour()
+            ));
+        }
+        out.push_str("</style>\n</head>\n<body>\n");
+
+        out.push_str(&format!("<p>{} matches</p>\n<p>", matches.len()));
+        for direction in Direction::ALL {
+            out.push_str(&format!(
+                "<span class=\"{:?}\">{:?}</span> ",
+                direction, direction
+            ));
+        }
+        out.push_str("</p>\n<pre>\n");
+
+        for (row, highlights) in self.board.iter().zip(self.highlights(matches)) {
+            for (letter, highlight) in row.iter().zip(highlights) {
+                let class = match highlight {
+                    Some(direction) => format!("{:?}", direction),
+                    None => "unused".to_string(),
+                };
+
+                out.push_str(&format!(
+                    "<span class=\"{}\">{}</span>",
+                    class,
+                    escape_html(*letter)
+                ));
+            }
+            out.push('\n');
+        }
+
+        out.push_str("</pre>\n</body>\n</html>\n");
+
+        out
+    }
+
+    /// Finds every placement of any of the template's variants
+    fn find_template(&self, template: &Template) -> Vec<Placement> {
+        let mut placements = vec![];
+
+        for y in 0..self.board.len() {
             for x in 0..self.board[y].len() {
-                let group = &[
-                    self.board[y][x],
-                    self.board[y + 1][x],
-                    self.board[y + 2][x],
-                    self.board[y + 3][x],
-                ];
-
-                if let &['S', 'A', 'M', 'X'] = &group {
-                    count += 1;
+                for (variant, shape) in template.variants.iter().enumerate() {
+                    if self.fits_shape(shape, (x, y)) {
+                        placements.push(Placement {
+                            position: (x, y),
+                            variant,
+                        });
+                    }
                 }
             }
         }
 
-        count
+        placements
+    }
+
+    fn fits_shape(&self, shape: &[Vec<Option<char>>], (x, y): (usize, usize)) -> bool {
+        shape.iter().enumerate().all(|(dy, row)| {
+            row.iter()
+                .enumerate()
+                .all(|(dx, cell)| match (cell, self.get(x + dx, y + dy)) {
+                    (None, Some(_)) => true,
+                    (Some(letter), Some(found)) => *letter == found,
+                    (_, None) => false,
+                })
+        })
     }
+}
+
+fn escape_html(letter: char) -> String {
+    match letter {
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '&' => "&amp;".to_string(),
+        '"' => "&quot;".to_string(),
+        _ => letter.to_string(),
+    }
+}
 
-    fn count_diagonal_northwest(&self) -> u64 {
-        let mut count = 0;
+/// Xorshift generator, enough for laying out puzzles reproducibly from a seed
+struct Rng {
+    state: u64,
+}
 
-        for y in 0..self.board.len() - 3 {
-            for x in 0..self.board[y].len() - 3 {
-                let group = &[
-                    self.board[y][x],
-                    self.board[y + 1][x + 1],
-                    self.board[y + 2][x + 2],
-                    self.board[y + 3][x + 3],
-                ];
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift gets stuck on zero, and nearby seeds should still start far apart
+        let state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
 
-                if let &['X', 'M', 'A', 'S'] = group {
-                    count += 1;
-                }
+        Self { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number in `0..bound`
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// Lays out a `width` by `height` wordsearch where each word reads exactly once in one of the
+/// directions, returning it along with where each word was put.
+///
+/// Words may share letters where they cross. The leftover cells get letters that don't spell
+/// out another copy of any word, and every attempt is checked with `find_words` before it's
+/// accepted
+fn generate(
+    width: usize,
+    height: usize,
+    words: &[&str],
+    directions: &[Direction],
+    seed: u64,
+) -> Option<(Wordsearch, Vec<Match>)> {
+    let mut rng = Rng::new(seed);
+    let letters = words
+        .iter()
+        .map(|word| word.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let mut alphabet = letters.concat();
+    alphabet.sort();
+    alphabet.dedup();
+    let fallback_alphabet = ('A'..='Z')
+        .filter(|letter| !alphabet.contains(letter))
+        .collect::<Vec<_>>();
+
+    // Longest words first, they're the hardest to fit
+    let mut order = (0..words.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| std::cmp::Reverse(letters[i].len()));
+
+    'attempts: for _ in 0..GENERATOR_ATTEMPTS {
+        let mut grid = vec![vec![None; width]; height];
+        let mut answers = vec![None; words.len()];
+
+        for &i in &order {
+            let Some(answer) = place_word(&mut grid, &letters[i], directions, &mut rng) else {
+                continue 'attempts;
+            };
+
+            answers[i] = Some(answer);
+        }
+
+        let mut blanks = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| grid[y][x].is_none())
+            .collect::<Vec<_>>();
+        rng.shuffle(&mut blanks);
+
+        for (x, y) in blanks {
+            let mut candidates = alphabet.clone();
+            rng.shuffle(&mut candidates);
+            let mut fallback = fallback_alphabet.clone();
+            rng.shuffle(&mut fallback);
+            candidates.extend(fallback);
+
+            let letter = candidates.into_iter().find(|&letter| {
+                grid[y][x] = Some(letter);
+                !spells_word(&grid, (x, y), &letters)
+            });
+
+            if letter.is_none() {
+                continue 'attempts;
             }
         }
 
-        count
+        let wordsearch = Wordsearch::from_grid(&grid);
+        let answers = answers.into_iter().collect::<Option<Vec<_>>>()?;
+
+        let sorted_cells = |m: &Match| {
+            let mut cells = m.cells().collect::<Vec<_>>();
+            cells.sort();
+            cells
+        };
+
+        let is_valid = wordsearch
+            .find_words(words)
+            .iter()
+            .zip(&answers)
+            .all(|(found, answer)| {
+                found.len() == 1 && sorted_cells(&found[0]) == sorted_cells(answer)
+            });
+
+        if is_valid {
+            return Some((wordsearch, answers));
+        }
+    }
+
+    None
+}
+
+/// Writes the word somewhere it fits, crossing other words only on matching letters
+fn place_word(
+    grid: &mut [Vec<Option<char>>],
+    word: &[char],
+    directions: &[Direction],
+    rng: &mut Rng,
+) -> Option<Match> {
+    let height = grid.len();
+    let width = grid.first().map(Vec::len).unwrap_or(0);
+
+    if width == 0 || height == 0 || directions.is_empty() {
+        return None;
+    }
+
+    for _ in 0..PLACEMENT_ATTEMPTS {
+        let answer = Match {
+            start: (rng.below(width), rng.below(height)),
+            direction: directions[rng.below(directions.len())],
+            len: word.len(),
+        };
+
+        let (dx, dy) = answer.direction.offset();
+        let end_x = answer.start.0 as isize + dx * (word.len() as isize - 1);
+        let end_y = answer.start.1 as isize + dy * (word.len() as isize - 1);
+
+        if !(0..width as isize).contains(&end_x) || !(0..height as isize).contains(&end_y) {
+            continue;
+        }
+
+        let fits = answer
+            .cells()
+            .zip(word)
+            .all(|((x, y), letter)| grid[y][x].is_none_or(|cell| cell == *letter));
+
+        if fits {
+            for ((x, y), letter) in answer.cells().zip(word) {
+                grid[y][x] = Some(*letter);
+            }
+
+            return Some(answer);
+        }
     }
 
-    fn count_diagonal_northwest_rev(&self) -> u64 {
-        let mut count = 0;
+    None
+}
+
+/// Whether any word now reads through the cell in any direction, using only filled cells
+fn spells_word(grid: &[Vec<Option<char>>], (x, y): (usize, usize), words: &[Vec<char>]) -> bool {
+    let get = |x: isize, y: isize| {
+        let row = grid.get(usize::try_from(y).ok()?)?;
+        *row.get(usize::try_from(x).ok()?)?
+    };
+
+    words.iter().any(|word| {
+        word.iter().enumerate().any(|(i, letter)| {
+            if grid[y][x] != Some(*letter) {
+                return false;
+            }
+
+            Direction::ALL.iter().any(|direction| {
+                let (dx, dy) = direction.offset();
+                let start_x = x as isize - dx * i as isize;
+                let start_y = y as isize - dy * i as isize;
+
+                word.iter().enumerate().all(|(j, letter)| {
+                    get(start_x + dx * j as isize, start_y + dy * j as isize) == Some(*letter)
+                })
+            })
+        })
+    })
+}
+
+/// A word the automaton looks for, either as written or reversed
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Pattern {
+    /// Index of the word among the automaton's unique words
+    word_index: usize,
+    is_reversed: bool,
+}
+
+#[derive(Debug, Default)]
+struct AutomatonNode {
+    children: HashMap<char, usize>,
+    /// Node for the longest proper suffix of this one's path that's also in the trie
+    fail: usize,
+    /// Nearest node along the fail links that ends a pattern
+    output_link: Option<usize>,
+    patterns: Vec<Pattern>,
+}
+
+/// Aho-Corasick automaton matching many words and their reverses in one pass over a line
+struct WordAutomaton {
+    nodes: Vec<AutomatonNode>,
+    word_lens: Vec<usize>,
+    /// Indices into the original word list for each unique word, for lists with duplicates
+    word_indices: Vec<Vec<usize>>,
+}
+
+impl WordAutomaton {
+    pub fn new(words: &[&str]) -> Self {
+        let mut automaton = Self {
+            nodes: vec![AutomatonNode::default()],
+            word_lens: vec![],
+            word_indices: vec![],
+        };
+
+        let mut unique_words: HashMap<&str, usize> = HashMap::new();
+
+        for (index, word) in words.iter().enumerate() {
+            if word.is_empty() {
+                continue;
+            }
+
+            if let Some(&word_index) = unique_words.get(word) {
+                automaton.word_indices[word_index].push(index);
+                continue;
+            }
 
-        for y in 0..self.board.len() - 3 {
-            for x in 0..self.board[y].len() - 3 {
-                let group = &[
-                    self.board[y][x],
-                    self.board[y + 1][x + 1],
-                    self.board[y + 2][x + 2],
-                    self.board[y + 3][x + 3],
-                ];
+            let word_index = automaton.word_lens.len();
+            unique_words.insert(word, word_index);
+            automaton.word_indices.push(vec![index]);
 
-                if let &['S', 'A', 'M', 'X'] = &group {
-                    count += 1;
+            let letters = word.chars().collect::<Vec<_>>();
+            let reversed = letters.iter().rev().copied().collect::<Vec<_>>();
+            automaton.word_lens.push(letters.len());
+
+            automaton.insert(
+                &letters,
+                Pattern {
+                    word_index,
+                    is_reversed: false,
+                },
+            );
+
+            if reversed != letters {
+                automaton.insert(
+                    &reversed,
+                    Pattern {
+                        word_index,
+                        is_reversed: true,
+                    },
+                );
+            }
+        }
+
+        automaton.link();
+
+        automaton
+    }
+
+    fn insert(&mut self, letters: &[char], pattern: Pattern) {
+        let mut node = 0;
+
+        for letter in letters {
+            node = match self.nodes[node].children.get(letter) {
+                Some(&child) => child,
+                None => {
+                    self.nodes.push(AutomatonNode::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children.insert(*letter, child);
+                    child
                 }
+            };
+        }
+
+        self.nodes[node].patterns.push(pattern);
+    }
+
+    /// Sets up the fail and output links breadth first, so every shorter suffix is done first
+    fn link(&mut self) {
+        let mut queue = self.nodes[0]
+            .children
+            .values()
+            .copied()
+            .collect::<VecDeque<_>>();
+
+        while let Some(node) = queue.pop_front() {
+            let children = self.nodes[node]
+                .children
+                .iter()
+                .map(|(letter, child)| (*letter, *child))
+                .collect::<Vec<_>>();
+
+            for (letter, child) in children {
+                let fail = self.step(self.nodes[node].fail, letter);
+
+                self.nodes[child].fail = fail;
+                self.nodes[child].output_link = if self.nodes[fail].patterns.is_empty() {
+                    self.nodes[fail].output_link
+                } else {
+                    Some(fail)
+                };
+
+                queue.push_back(child);
             }
         }
+    }
+
+    fn step(&self, mut node: usize, letter: char) -> usize {
+        loop {
+            if let Some(&child) = self.nodes[node].children.get(&letter) {
+                return child;
+            }
+
+            if node == 0 {
+                return 0;
+            }
 
-        count
+            node = self.nodes[node].fail;
+        }
     }
 
-    fn count_diagonal_southeast(&self) -> u64 {
-        let mut count = 0;
+    /// Runs the automaton over a line, returning the index each pattern ends at. A missing
+    /// letter (e.g. past the end of a short row) breaks the line in two
+    pub fn find(&self, letters: impl Iterator<Item = Option<char>>) -> Vec<(usize, Pattern)> {
+        let mut found = vec![];
+        let mut node = 0;
 
-        for y in 3..self.board.len() {
-            for x in 0..self.board[y].len() - 3 {
-                let group = &[
-                    self.board[y][x],
-                    self.board[y - 1][x + 1],
-                    self.board[y - 2][x + 2],
-                    self.board[y - 3][x + 3],
-                ];
+        for (i, letter) in letters.enumerate() {
+            let Some(letter) = letter else {
+                node = 0;
+                continue;
+            };
 
-                if let &['X', 'M', 'A', 'S'] = group {
-                    count += 1;
+            node = self.step(node, letter);
+
+            let mut output = Some(node);
+            while let Some(output_node) = output {
+                for pattern in &self.nodes[output_node].patterns {
+                    found.push((i, *pattern));
                 }
+
+                output = self.nodes[output_node].output_link;
             }
         }
 
-        count
+        found
     }
+}
 
-    fn count_diagonal_southeast_rev(&self) -> u64 {
-        let mut count = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for y in 3..self.board.len() {
-            for x in 0..self.board[y].len() - 3 {
-                let group = &[
-                    self.board[y][x],
-                    self.board[y - 1][x + 1],
-                    self.board[y - 2][x + 2],
-                    self.board[y - 3][x + 3],
-                ];
+    fn random_grid(seed: u64, width: usize, height: usize, letters: &[char]) -> String {
+        let mut rng = Rng::new(seed);
+        let mut grid = String::new();
 
-                if let &['S', 'A', 'M', 'X'] = &group {
-                    count += 1;
-                }
+        for _ in 0..height {
+            for _ in 0..width {
+                grid.push(letters[rng.below(letters.len())]);
             }
+            grid.push('\n');
         }
 
-        count
+        grid
     }
 
-    fn count_x_mas(&self) -> u64 {
-        let mut count = 0;
+    const EXAMPLE: &str = "MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX";
 
-        for y in 1..self.board.len() - 1 {
-            for x in 1..self.board[y].len() - 1 {
-                let middle = self.board[y][x];
-                let northwest = (self.board[y - 1][x - 1], self.board[y + 1][x + 1]);
-                let southeast = (self.board[y + 1][x - 1], self.board[y - 1][x + 1]);
+    #[test]
+    fn example_counts() {
+        let wordsearch = Wordsearch::new(EXAMPLE);
+        let x_mas = Template::new(Wordsearch::X_MAS);
 
-                match (middle, northwest, southeast) {
-                    ('A', ('M', 'S'), ('M', 'S'))
-                    | ('A', ('S', 'M'), ('M', 'S'))
-                    | ('A', ('M', 'S'), ('S', 'M'))
-                    | ('A', ('S', 'M'), ('S', 'M')) => count += 1,
-                    _ => (),
-                };
+        assert_eq!(wordsearch.find_word("XMAS", &Direction::ALL).len(), 18);
+        assert_eq!(x_mas.variants.len(), 4);
+        assert_eq!(wordsearch.find_template(&x_mas).len(), 9);
+    }
+
+    #[test]
+    fn render_plain_example() {
+        let wordsearch = Wordsearch::new(EXAMPLE);
+        let matches = wordsearch.find_word("XMAS", &Direction::ALL);
+
+        let expected = "....XXMAS.
+.SAMXMS...
+...S..A...
+..A.A.MS.X
+XMASAMX.MM
+X.....XA.A
+S.S.S.S.SS
+.A.A.A.A.A
+..M.M.M.MM
+.X.X.XMASX
+";
+
+        assert_eq!(wordsearch.render_plain(&matches), expected);
+    }
+
+    #[test]
+    fn find_words_matches_find_word() {
+        let grid = random_grid(42, 23, 17, &['X', 'M', 'A', 'S']);
+        let wordsearch = Wordsearch::new(&grid);
+        let words = ["XMAS", "MAS", "SAM", "AMA", "S", "XMAS", "AAAA", "MASAMAS"];
+
+        let matches = wordsearch.find_words(&words);
+
+        for (word, mut found) in words.iter().zip(matches) {
+            let mut expected = wordsearch.find_word(word, &Direction::ALL);
+
+            // find_word matches palindromes both ways, find_words only once
+            let is_palindrome = word.chars().eq(word.chars().rev());
+            if is_palindrome {
+                expected.retain(|m| Direction::LINES.contains(&m.direction));
             }
+            if word.len() == 1 {
+                expected.retain(|m| m.direction == Direction::East);
+            }
+
+            let key = |m: &Match| (m.start, m.direction as u8);
+            expected.sort_by_key(key);
+            found.sort_by_key(key);
+
+            assert_eq!(found, expected, "{}", word);
         }
+    }
+
+    #[test]
+    fn generated_grid_has_each_word_once() {
+        let words = [
+            "XMAS", "SANTA", "ELF", "REINDEER", "SLEIGH", "TINSEL", "ANNA",
+        ];
+
+        for seed in 0..5 {
+            let (wordsearch, answers) =
+                generate(12, 10, &words, &Direction::ALL, seed).expect("grid");
 
-        count
+            for ((word, found), answer) in words
+                .iter()
+                .zip(wordsearch.find_words(&words))
+                .zip(&answers)
+            {
+                assert_eq!(found.len(), 1, "{} in seed {}", word, seed);
+
+                let spelt = answer
+                    .cells()
+                    .map(|(x, y)| wordsearch.board[y][x])
+                    .collect::<String>();
+                assert_eq!(spelt, *word);
+            }
+
+            let (again, _) = generate(12, 10, &words, &Direction::ALL, seed).unwrap();
+            assert_eq!(again.board, wordsearch.board);
+        }
     }
 }
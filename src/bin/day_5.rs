@@ -1,13 +1,161 @@
-use std::collections::{HashMap, HashSet};
-use std::fs;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::Hash;
+use std::str::FromStr;
+use std::{env, fs};
+
+/// Largest manual whose orderings are counted, the count goes through every subset of its pages
+const MAX_COUNTED_PAGES: usize = 20;
+const DEFAULT_ORDERING_LIMIT: usize = 10;
 
 fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // Pages are numbers unless asked to treat them as arbitrary labels
+    let labels = args.iter().any(|arg| arg == "--labels");
+    args.retain(|arg| arg != "--labels");
+
     let input = fs::read_to_string("Input_Day_5").unwrap();
+
+    if labels {
+        run::<String>(&args, &input);
+    } else {
+        run::<u32>(&args, &input);
+    }
+}
+
+/// What a page can be identified by, numbers as in the puzzle or any other label
+trait PageId: Clone + Eq + Hash + Display + FromStr<Err: Debug> {
+    /// The page's number for the middle page sums, if it has one
+    fn number(&self) -> Option<u64>;
+}
+
+impl PageId for u32 {
+    fn number(&self) -> Option<u64> {
+        Some(u64::from(*self))
+    }
+}
+
+impl PageId for String {
+    fn number(&self) -> Option<u64> {
+        self.parse().ok()
+    }
+}
+
+/// Shows a middle page sum, which is `None` when any of the pages wasn't numbered
+fn format_sum(sum: Option<u64>) -> String {
+    match sum {
+        Some(sum) => sum.to_string(),
+        None => "n/a".to_string(),
+    }
+}
+
+fn run<P: PageId>(args: &[String], input: &str) {
+    let (rules, manuals) = parse_input::<P>(input);
+
+    match args.first().map(String::as_str) {
+        Some("report") => {
+            let path = args.get(1).map(String::as_str).unwrap_or("Report_Day_5.md");
+            fs::write(path, report(&rules, &manuals)).unwrap();
+            return;
+        }
+        Some("explain") => {
+            explain(&rules, &manuals);
+            return;
+        }
+        Some("dot") => {
+            let mut manual = None;
+            let mut reduce = false;
+            let mut path = "Rules_Day_5.dot";
+
+            let mut dot_args = args.iter().skip(1);
+            while let Some(arg) = dot_args.next() {
+                match arg.as_str() {
+                    "--manual" => match dot_args.next().map(|n| n.parse::<usize>()) {
+                        Some(Ok(n)) if (1..=manuals.len()).contains(&n) => {
+                            manual = Some(&manuals[n - 1])
+                        }
+                        _ => {
+                            eprintln!(
+                                "Usage: day_5 dot [--manual <1-{}>] [--reduce] [output]",
+                                manuals.len()
+                            );
+                            return;
+                        }
+                    },
+                    "--reduce" => reduce = true,
+                    _ => path = arg,
+                }
+            }
+
+            fs::write(path, rules.to_dot(manual, reduce)).unwrap();
+            println!("Wrote {}", path);
+            return;
+        }
+        Some("orderings") => {
+            match args.get(1).map(|manual| manual.parse::<usize>()) {
+                None => manuals
+                    .iter()
+                    .enumerate()
+                    .for_each(|(i, manual)| print_ordering_summary(i, manual, &rules)),
+                Some(Ok(number)) if (1..=manuals.len()).contains(&number) => {
+                    let limit = args
+                        .get(2)
+                        .and_then(|limit| limit.parse::<usize>().ok())
+                        .unwrap_or(DEFAULT_ORDERING_LIMIT);
+
+                    let manual = &manuals[number - 1];
+                    print_ordering_summary(number - 1, manual, &rules);
+
+                    for ordering in manual.orderings(&rules).take(limit) {
+                        println!("    {}", ordering);
+                    }
+                }
+                _ => eprintln!(
+                    "Usage: day_5 orderings [<manual 1-{}> [limit]]",
+                    manuals.len()
+                ),
+            }
+            return;
+        }
+        _ => (),
+    }
+
+    let total: Option<u64> = manuals
+        .iter()
+        .filter(|m| m.is_valid(&rules))
+        .map(|m| m.get_middle().number())
+        .sum();
+
+    println!("Middle Sum: {}", format_sum(total));
+
+    let total_fixed: Option<u64> = manuals
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| !m.is_valid(&rules))
+        .filter_map(|(i, m)| match m.fix(&rules) {
+            Ok(fixed) => Some(fixed),
+            Err(cycle) => {
+                eprintln!("Manual {} can't be fixed: {}", i + 1, cycle);
+                None
+            }
+        })
+        .map(|m| m.get_middle().number())
+        .sum();
+
+    println!("Fixed Middle Sum: {}", format_sum(total_fixed));
+}
+
+fn parse_input<P: PageId>(input: &str) -> (Rules<P>, Vec<Manual<P>>) {
     let (rules, manuals) = input.split_once("\n\n").unwrap();
 
     let rules_iter = rules.lines().map(|s| {
         let (before, after) = s.split_once('|').unwrap();
-        (before.parse::<u8>().unwrap(), after.parse::<u8>().unwrap())
+        (
+            before.trim().parse::<P>().unwrap(),
+            after.trim().parse::<P>().unwrap(),
+        )
     });
 
     let rules = Rules::new(rules_iter);
@@ -15,136 +163,831 @@ fn main() {
     let manuals: Vec<_> = manuals
         .lines()
         .map(|l| {
-            let page_nums: Vec<_> = l.split(',').map(|n| n.parse::<u8>().unwrap()).collect();
+            let page_nums: Vec<_> = l
+                .split(',')
+                .map(|n| n.trim().parse::<P>().unwrap())
+                .collect();
             Manual::new(page_nums)
         })
         .collect();
 
-    let total: u16 = manuals
-        .iter()
-        .filter(|m| m.is_valid(&rules))
-        .map(Manual::get_middle)
-        .map(u16::from)
-        .sum();
+    (rules, manuals)
+}
 
-    println!("Middle Sum: {}", total);
+/// Prints every invalid manual with the rules it breaks and its corrected order
+fn explain<P: PageId>(rules: &Rules<P>, manuals: &[Manual<P>]) {
+    for (i, manual) in manuals.iter().enumerate() {
+        let violations = manual.violations(rules);
 
-    let total_fixed: u16 = manuals
-        .iter()
-        .filter(|m| !m.is_valid(&rules))
-        .map(|m| m.fix(&rules))
-        .map(|m| m.get_middle())
-        .map(u16::from)
-        .sum();
+        if violations.is_empty() {
+            continue;
+        }
+
+        println!("Manual {}: {}", i + 1, manual);
+
+        for violation in &violations {
+            println!("    {}", violation);
+        }
 
-    println!("Fixed Middle Sum: {}", total_fixed);
+        match manual.fix(rules) {
+            Ok(fixed) => println!("    Corrected: {}", fixed),
+            Err(cycle) => println!("    Can't be corrected: {}", cycle),
+        }
+
+        println!();
+    }
 }
 
-struct Rules {
-    before_to_after: HashMap<u8, HashSet<u8>>,
-    after_to_before: HashMap<u8, HashSet<u8>>,
+/// Prints how many ways the manual's pages could be ordered and which pages could end up in
+/// the middle
+fn print_ordering_summary<P: PageId>(i: usize, manual: &Manual<P>, rules: &Rules<P>) {
+    let count = match manual.count_orderings(rules) {
+        Some(count) => format!("{} orderings", count),
+        None => format!(
+            "too many pages to count orderings (over {})",
+            MAX_COUNTED_PAGES
+        ),
+    };
+
+    let middles = manual.possible_middles(rules);
+    let middle = match middles.as_slice() {
+        [] => "no middle, the rules form a cycle".to_string(),
+        [middle] => format!("middle is always {}", middle),
+        _ => format!(
+            "middle could be any of {}",
+            middles
+                .iter()
+                .map(|page| page.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+    };
+
+    println!(
+        "Manual {} ({} pages): {}, {}",
+        i + 1,
+        manual.pages.len(),
+        count,
+        middle
+    );
 }
 
-impl Rules {
-    pub fn new(it: impl Iterator<Item = (u8, u8)>) -> Self {
-        let mut before_to_after: HashMap<u8, HashSet<u8>> = HashMap::new();
-        let mut after_to_before: HashMap<u8, HashSet<u8>> = HashMap::new();
+/// Builds a markdown report listing every invalid manual alongside its fixed ordering
+fn report<P: PageId>(rules: &Rules<P>, manuals: &[Manual<P>]) -> String {
+    let mut out = String::from("# Day 5: Print Queue\n\n");
+    out.push_str("## Invalid Manuals\n\n");
+    out.push_str("| Manual | Pages | Fixed Order | Fixed Middle |\n");
+    out.push_str("|---:|---|---|---:|\n");
 
-        it.for_each(|(before, after)| {
-            match before_to_after.get_mut(&before) {
-                Some(set) => {
-                    set.insert(after);
-                }
-                None => {
-                    let mut set = HashSet::new();
-                    set.insert(after);
-                    before_to_after.insert(before, set);
-                }
-            };
+    let mut valid_count = 0;
+    let mut total = Some(0);
+    let mut total_fixed = Some(0);
 
-            match after_to_before.get_mut(&after) {
-                Some(set) => {
-                    set.insert(before);
-                }
-                None => {
-                    let mut set = HashSet::new();
-                    set.insert(before);
-                    after_to_before.insert(after, set);
-                }
-            };
-        });
+    for (i, manual) in manuals.iter().enumerate() {
+        if manual.is_valid(rules) {
+            valid_count += 1;
+            total = total.zip(manual.get_middle().number()).map(|(a, b)| a + b);
+            continue;
+        }
+
+        match manual.fix(rules) {
+            Ok(fixed) => {
+                total_fixed = total_fixed
+                    .zip(fixed.get_middle().number())
+                    .map(|(a, b)| a + b);
+
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    i + 1,
+                    manual,
+                    fixed,
+                    fixed.get_middle()
+                ));
+            }
+            Err(cycle) => out.push_str(&format!("| {} | {} | {} | - |\n", i + 1, manual, cycle)),
+        }
+    }
+
+    out.push_str(&format!(
+        "\n{} of {} manuals were already valid\n\n",
+        valid_count,
+        manuals.len()
+    ));
+    out.push_str(&format!("- Middle Sum: {}\n", format_sum(total)));
+    out.push_str(&format!(
+        "- Fixed Middle Sum: {}\n",
+        format_sum(total_fixed)
+    ));
+
+    out
+}
+
+/// Fixed size set of page ids, one bit each
+#[derive(Debug, Clone)]
+struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    pub fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    pub fn intersects(&self, other: &BitSet) -> bool {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .any(|(lhs, rhs)| lhs & rhs != 0)
+    }
+}
+
+/// The rules with every page that shows up in one given a dense id, so all of a page's rules
+/// fit in a row of bits
+struct Rules<P> {
+    pages: Vec<P>,
+    ids: HashMap<P, usize>,
+    /// Bit `j` of row `i` is set when page `i` must come before page `j`
+    before_to_after: Vec<BitSet>,
+}
+
+impl<P: PageId> Rules<P> {
+    pub fn new(it: impl Iterator<Item = (P, P)>) -> Self {
+        let mut pages = vec![];
+        let mut ids = HashMap::new();
+
+        let mut id_of = |page: P| {
+            *ids.entry(page.clone()).or_insert_with(|| {
+                pages.push(page);
+                pages.len() - 1
+            })
+        };
+
+        let pairs = it
+            .map(|(before, after)| (id_of(before), id_of(after)))
+            .collect::<Vec<_>>();
+
+        let mut before_to_after = vec![BitSet::new(pages.len()); pages.len()];
+        for (before, after) in pairs {
+            before_to_after[before].insert(after);
+        }
 
         Self {
+            pages,
+            ids,
             before_to_after,
-            after_to_before,
         }
     }
 
-    pub fn before_page(&self, page: u8) -> Option<&HashSet<u8>> {
-        self.after_to_before.get(&page)
+    /// The page's id, or `None` if no rule mentions it
+    pub fn id(&self, page: &P) -> Option<usize> {
+        self.ids.get(page).copied()
     }
 
-    pub fn after_page(&self, page: u8) -> Option<&HashSet<u8>> {
-        self.before_to_after.get(&page)
+    /// Writes the rule graph in Graphviz DOT, with an edge from each page to every page that
+    /// must come after it.
+    ///
+    /// Given a manual only its pages are drawn, with the rules it breaks in red. Reducing drops
+    /// the edges already implied by a longer path, apart from broken ones, but only when the
+    /// rules don't form a cycle since then there's no single reduction
+    pub fn to_dot(&self, manual: Option<&Manual<P>>, reduce: bool) -> String {
+        let pages = match manual {
+            Some(manual) => manual.pages.iter().collect::<Vec<_>>(),
+            None => self.pages.iter().collect(),
+        };
+
+        let ids = pages.iter().map(|page| self.id(page)).collect::<Vec<_>>();
+        let pages_after = (0..ids.len())
+            .map(|i| {
+                (0..ids.len())
+                    .filter(|j| self.precedes(ids[i], ids[*j]))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Pages are in the manual's order, so an edge going backwards is a broken rule
+        let is_broken = |i: usize, j: usize| manual.is_some() && j < i;
+
+        let reachable = reachability(&pages_after);
+        let is_cyclic = (0..pages.len()).any(|i| reachable[i].contains(i));
+
+        let mut out = String::from("digraph rules {\n");
+        out.push_str("    node [shape=box];\n");
+
+        if reduce && is_cyclic {
+            out.push_str("    // The rules form a cycle, so the graph isn't reduced\n");
+        }
+
+        for page in &pages {
+            out.push_str(&format!("    {};\n", dot_id(page)));
+        }
+
+        for (i, after) in pages_after.iter().enumerate() {
+            for &j in after {
+                let is_implied = after.iter().any(|&k| k != j && reachable[k].contains(j));
+
+                if reduce && !is_cyclic && is_implied && !is_broken(i, j) {
+                    continue;
+                }
+
+                let style = if is_broken(i, j) { " [color=red]" } else { "" };
+                out.push_str(&format!(
+                    "    {} -> {}{};\n",
+                    dot_id(pages[i]),
+                    dot_id(pages[j]),
+                    style
+                ));
+            }
+        }
+
+        out.push_str("}\n");
+
+        out
+    }
+
+    /// Whether a rule says the page with the first id must come before the one with the second
+    pub fn precedes(&self, before: Option<usize>, after: Option<usize>) -> bool {
+        match (before, after) {
+            (Some(before), Some(after)) => self.before_to_after[before].contains(after),
+            _ => false,
+        }
     }
 }
 
-struct Manual {
-    pages: Vec<u8>,
+/// A `before|after` rule broken by `after` showing up earlier in the manual than `before`,
+/// with both pages' 0-based positions
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Violation<P> {
+    before: P,
+    after: P,
+    before_position: usize,
+    after_position: usize,
 }
 
-impl Manual {
-    pub fn new(nums: Vec<u8>) -> Self {
-        Self { pages: nums }
+impl<P: Display> Display for Violation<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}: {} is page {} but {} is page {}",
+            self.before,
+            self.after,
+            self.before,
+            self.before_position + 1,
+            self.after,
+            self.after_position + 1
+        )
     }
+}
+
+/// Pages whose rules each say it must come before the next one, and the last before the first,
+/// so no ordering of them can satisfy every rule
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct RuleCycle<P> {
+    pages: Vec<P>,
+}
 
-    pub fn is_valid(&self, rules: &Rules) -> bool {
-        let mut before_current_page = vec![];
+impl<P: Display> Display for RuleCycle<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "rules form a cycle ")?;
 
         for page in &self.pages {
-            if let Some(pages_after_rules) = rules.after_page(*page) {
-                for page in pages_after_rules {
-                    if let Ok(_) = before_current_page.binary_search(page) {
-                        return false;
-                    }
+            write!(f, "{} -> ", page)?;
+        }
+
+        write!(f, "{}", self.pages[0])
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Manual<P> {
+    pages: Vec<P>,
+}
+
+impl<P: PageId> Manual<P> {
+    pub fn new(nums: Vec<P>) -> Self {
+        Self { pages: nums }
+    }
+
+    /// Checks each page against the set of pages before it in one go, rather than pair by pair
+    pub fn is_valid(&self, rules: &Rules<P>) -> bool {
+        let mut seen = BitSet::new(rules.pages.len());
+
+        self.pages
+            .iter()
+            .filter_map(|page| rules.id(page))
+            .all(|id| {
+                let is_ordered = !rules.before_to_after[id].intersects(&seen);
+                seen.insert(id);
+                is_ordered
+            })
+    }
+
+    fn ids(&self, rules: &Rules<P>) -> Vec<Option<usize>> {
+        self.pages.iter().map(|page| rules.id(page)).collect()
+    }
+
+    /// Every rule broken by a pair of pages in the manual, ordered by where the page that
+    /// should've come later shows up
+    pub fn violations(&self, rules: &Rules<P>) -> Vec<Violation<P>> {
+        let ids = self.ids(rules);
+        let mut violations = vec![];
+
+        for after_position in 0..ids.len() {
+            for before_position in after_position + 1..ids.len() {
+                if rules.precedes(ids[before_position], ids[after_position]) {
+                    violations.push(Violation {
+                        before: self.pages[before_position].clone(),
+                        after: self.pages[after_position].clone(),
+                        before_position,
+                        after_position,
+                    });
                 }
             }
+        }
+
+        violations
+    }
+
+    pub fn get_middle(&self) -> &P {
+        let index = self.pages.len() / 2;
+
+        &self.pages[index]
+    }
+
+    /// Orders the pages with Kahn's algorithm over the rules between them. Whenever several
+    /// pages could go next the one earliest in the manual goes first, so valid manuals are left
+    /// as they are
+    pub fn fix(&self, rules: &Rules<P>) -> Result<Self, RuleCycle<P>> {
+        let page_count = self.pages.len();
+        let pages_after = self.pages_after(rules);
+        let mut in_degrees = in_degrees(&pages_after);
+
+        let mut ready = (0..page_count)
+            .filter(|i| in_degrees[*i] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+        let mut ordered_pages = Vec::with_capacity(page_count);
 
-            if let Err(index) = before_current_page.binary_search(page) {
-                before_current_page.insert(index, *page);
+        while let Some(Reverse(i)) = ready.pop() {
+            ordered_pages.push(self.pages[i].clone());
+
+            for &j in &pages_after[i] {
+                in_degrees[j] -= 1;
+
+                if in_degrees[j] == 0 {
+                    ready.push(Reverse(j));
+                }
             }
         }
 
-        true
+        if ordered_pages.len() < page_count {
+            return Err(self.find_cycle(&pages_after, &in_degrees));
+        }
+
+        Ok(Self::new(ordered_pages))
     }
 
-    pub fn get_middle(&self) -> u8 {
-        let index = self.pages.len() / 2;
+    /// For each position, the positions of the pages the rules say must come after it
+    fn pages_after(&self, rules: &Rules<P>) -> Vec<Vec<usize>> {
+        let ids = self.ids(rules);
 
-        self.pages[index]
+        (0..ids.len())
+            .map(|i| {
+                (0..ids.len())
+                    .filter(|j| rules.precedes(ids[i], ids[*j]))
+                    .collect()
+            })
+            .collect()
     }
 
-    pub fn fix(&self, rules: &Rules) -> Self {
-        let mut ordered_pages = vec![];
+    /// How many orderings of the pages follow every rule, or `None` if the manual has too many
+    /// pages to go through every subset of them.
+    ///
+    /// Counts the ways to order each subset that could make up the start of a valid ordering,
+    /// building up from the empty set one page at a time
+    pub fn count_orderings(&self, rules: &Rules<P>) -> Option<u128> {
+        let page_count = self.pages.len();
 
-        for page in &self.pages {
-            if let Some(page_after_rules) = rules.after_page(*page) {
-                let mut index = 0;
+        if page_count > MAX_COUNTED_PAGES {
+            return None;
+        }
 
-                for ordered_page in &ordered_pages {
-                    if page_after_rules.contains(ordered_page) {
-                        break;
-                    }
+        let mut required_before = vec![0_usize; page_count];
+        for (i, after) in self.pages_after(rules).iter().enumerate() {
+            for j in after {
+                required_before[*j] |= 1 << i;
+            }
+        }
+
+        let mut counts = vec![0_u128; 1 << page_count];
+        counts[0] = 1;
+
+        for placed in 0..counts.len() {
+            if counts[placed] == 0 {
+                continue;
+            }
+
+            for (i, before) in required_before.iter().enumerate() {
+                if placed & (1 << i) == 0 && before & placed == *before {
+                    counts[placed | (1 << i)] += counts[placed];
+                }
+            }
+        }
+
+        Some(counts[counts.len() - 1])
+    }
+
+    /// Lazily yields every ordering that follows the rules, in order of which pages show up
+    /// earliest in the manual. Rules with a cycle yield nothing, without searching through the
+    /// orderings of the pages outside it
+    pub fn orderings(&self, rules: &Rules<P>) -> Orderings<'_, P> {
+        let pages_after = self.pages_after(rules);
+        let has_cycle = reachability(&pages_after)
+            .iter()
+            .enumerate()
+            .any(|(i, reachable)| reachable.contains(i));
+
+        Orderings {
+            manual: self,
+            in_degrees: in_degrees(&pages_after),
+            pages_after,
+            is_placed: vec![false; self.pages.len()],
+            path: vec![],
+            next_candidate: 0,
+            is_done: has_cycle,
+        }
+    }
+
+    /// Every page that's in the middle of at least one valid ordering. A page can go anywhere
+    /// between the number of pages that must come before it and the number that must come
+    /// after it, counting rules that only apply through other pages
+    pub fn possible_middles(&self, rules: &Rules<P>) -> Vec<&P> {
+        let page_count = self.pages.len();
+        let pages_after = self.pages_after(rules);
+
+        let reachable = reachability(&pages_after);
+
+        if (0..page_count).any(|i| reachable[i].contains(i)) {
+            return vec![];
+        }
+
+        let middle = page_count / 2;
+
+        (0..page_count)
+            .filter(|&i| {
+                let after_count = (0..page_count)
+                    .filter(|&j| reachable[i].contains(j))
+                    .count();
+                let before_count = (0..page_count)
+                    .filter(|&j| reachable[j].contains(i))
+                    .count();
+
+                before_count <= middle && middle + after_count < page_count
+            })
+            .map(|i| &self.pages[i])
+            .collect()
+    }
+
+    /// Finds a cycle among the pages Kahn's algorithm couldn't place. Each of them still has
+    /// an unplaced page that must come before it, so walking back through those has to end up
+    /// somewhere it's already been
+    fn find_cycle(&self, pages_after: &[Vec<usize>], in_degrees: &[usize]) -> RuleCycle<P> {
+        let is_unplaced = |i: usize| in_degrees[i] > 0;
+        let mut path = vec![(0..self.pages.len()).find(|i| is_unplaced(*i)).unwrap()];
+
+        loop {
+            let current = *path.last().unwrap();
+            let previous = (0..self.pages.len())
+                .find(|i| is_unplaced(*i) && pages_after[*i].contains(&current))
+                .unwrap();
+
+            if let Some(start) = path.iter().position(|i| *i == previous) {
+                let pages = path[start..]
+                    .iter()
+                    .rev()
+                    .map(|i| self.pages[*i].clone())
+                    .collect();
+
+                return RuleCycle { pages };
+            }
+
+            path.push(previous);
+        }
+    }
+}
+
+/// How many pages must come before each one
+fn in_degrees(pages_after: &[Vec<usize>]) -> Vec<usize> {
+    let mut in_degrees = vec![0; pages_after.len()];
+
+    for after in pages_after {
+        for j in after {
+            in_degrees[*j] += 1;
+        }
+    }
+
+    in_degrees
+}
 
-                    index += 1;
+/// Every page reachable from each one by following the rules, which includes itself only when
+/// it's part of a cycle
+fn reachability(pages_after: &[Vec<usize>]) -> Vec<BitSet> {
+    let page_count = pages_after.len();
+
+    (0..page_count)
+        .map(|start| {
+            let mut seen = BitSet::new(page_count);
+            let mut stack = vec![start];
+
+            while let Some(i) = stack.pop() {
+                for &j in &pages_after[i] {
+                    if !seen.contains(j) {
+                        seen.insert(j);
+                        stack.push(j);
+                    }
                 }
+            }
+
+            seen
+        })
+        .collect()
+}
+
+/// Quotes a page for use as a DOT node id
+fn dot_id(page: &impl Display) -> String {
+    format!(
+        "\"{}\"",
+        page.to_string().replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// Backtracking search through a manual's valid orderings, only going as far as the next one
+/// each time it's asked
+struct Orderings<'a, P> {
+    manual: &'a Manual<P>,
+    pages_after: Vec<Vec<usize>>,
+    /// How many unplaced pages must still come before each one
+    in_degrees: Vec<usize>,
+    is_placed: Vec<bool>,
+    /// Positions of the pages placed so far
+    path: Vec<usize>,
+    /// The position to try placing next at the end of the path
+    next_candidate: usize,
+    is_done: bool,
+}
+
+impl<P> Orderings<'_, P> {
+    fn place(&mut self, i: usize) {
+        self.is_placed[i] = true;
+        self.path.push(i);
+        self.pages_after[i]
+            .iter()
+            .for_each(|j| self.in_degrees[*j] -= 1);
+        self.next_candidate = 0;
+    }
+
+    /// Takes back the last page placed, so the page after it gets tried in its spot
+    fn backtrack(&mut self) {
+        let Some(i) = self.path.pop() else {
+            self.is_done = true;
+            return;
+        };
+
+        self.is_placed[i] = false;
+        self.pages_after[i]
+            .iter()
+            .for_each(|j| self.in_degrees[*j] += 1);
+        self.next_candidate = i + 1;
+    }
+}
+
+impl<P: Clone> Iterator for Orderings<'_, P> {
+    type Item = Manual<P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page_count = self.is_placed.len();
+
+        while !self.is_done {
+            if self.path.len() == page_count {
+                let pages = self
+                    .path
+                    .iter()
+                    .map(|i| self.manual.pages[*i].clone())
+                    .collect();
+                self.backtrack();
 
-                ordered_pages.insert(index, *page);
-            } else {
-                ordered_pages.push(*page);
+                return Some(Manual { pages });
+            }
+
+            match (self.next_candidate..page_count)
+                .find(|i| !self.is_placed[*i] && self.in_degrees[*i] == 0)
+            {
+                Some(i) => self.place(i),
+                None => self.backtrack(),
             }
         }
 
-        Self::new(ordered_pages)
+        None
+    }
+}
+
+impl<P: Display> Display for Manual<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let pages = self
+            .pages
+            .iter()
+            .map(P::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        write!(f, "{}", pages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47";
+
+    #[test]
+    fn fix_example() {
+        let (rules, manuals) = parse_input::<u32>(EXAMPLE);
+
+        let fixed = manuals
+            .iter()
+            .map(|m| m.fix(&rules).unwrap().pages)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            fixed,
+            [
+                vec![75, 47, 61, 53, 29],
+                vec![97, 61, 53, 29, 13],
+                vec![75, 29, 13],
+                vec![97, 75, 47, 61, 53],
+                vec![61, 29, 13],
+                vec![97, 75, 47, 29, 13],
+            ]
+        );
+    }
+
+    #[test]
+    fn violations_example() {
+        let (rules, manuals) = parse_input::<u32>(EXAMPLE);
+
+        let violations = manuals
+            .iter()
+            .map(|m| {
+                m.violations(&rules)
+                    .iter()
+                    .map(|v| (v.before, v.after, v.before_position, v.after_position))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            violations,
+            [
+                vec![],
+                vec![],
+                vec![],
+                vec![(97, 75, 1, 0)],
+                vec![(29, 13, 2, 1)],
+                vec![
+                    (75, 13, 2, 1),
+                    (29, 13, 3, 1),
+                    (47, 13, 4, 1),
+                    (47, 29, 4, 3)
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_and_large_pages() {
+        let input = "cover|intro\nintro|index\n1000|70000\n\nindex,intro,cover\n70000,5,1000";
+        let (rules, manuals) = parse_input::<String>(input);
+
+        let fixed = manuals
+            .iter()
+            .map(|m| m.fix(&rules).unwrap().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(fixed, ["cover,intro,index", "5,1000,70000"]);
+        assert_eq!(manuals[1].get_middle().number(), Some(5));
+
+        let (rules, manuals) = parse_input::<u32>("1000|70000\n\n70000,5,1000");
+        assert!(!manuals[0].is_valid(&rules));
+        assert_eq!(manuals[0].violations(&rules)[0].before, 1000);
+    }
+
+    #[test]
+    fn orderings() {
+        let (rules, manuals) = parse_input::<u32>("1|2\n1|3\n2|4\n3|4\n\n4,3,2,1\n5,3,1\n75,97\n");
+
+        let orderings = manuals[0]
+            .orderings(&rules)
+            .map(|m| m.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(orderings, ["1,3,2,4", "1,2,3,4"]);
+        assert_eq!(manuals[0].count_orderings(&rules), Some(2));
+        assert_eq!(manuals[0].possible_middles(&rules), [&3, &2]);
+
+        assert_eq!(manuals[1].count_orderings(&rules), Some(3));
+        assert_eq!(manuals[1].orderings(&rules).count(), 3);
+        assert_eq!(manuals[1].possible_middles(&rules), [&5, &3, &1]);
+
+        assert_eq!(manuals[2].count_orderings(&rules), Some(2));
+        assert_eq!(manuals[2].possible_middles(&rules).len(), 2);
+
+        let (rules, manuals) = parse_input::<u32>(EXAMPLE);
+        for manual in &manuals {
+            let fixed = manual.fix(&rules).unwrap();
+
+            assert_eq!(manual.count_orderings(&rules), Some(1));
+            assert_eq!(manual.possible_middles(&rules), [fixed.get_middle()]);
+        }
+
+        let (rules, manuals) = parse_input::<u32>("1|2\n2|3\n3|1\n\n1,2,3,4");
+        assert_eq!(manuals[0].count_orderings(&rules), Some(0));
+        assert_eq!(manuals[0].orderings(&rules).count(), 0);
+        assert!(manuals[0].possible_middles(&rules).is_empty());
+
+        // Searching the free pages' orderings before finding the cycle would take 14! steps
+        let (rules, manuals) =
+            parse_input::<u32>("1|2\n2|3\n3|1\n\n4,5,6,7,8,9,10,11,12,13,14,15,16,17,1,2,3");
+        assert_eq!(manuals[0].orderings(&rules).count(), 0);
+    }
+
+    #[test]
+    fn dot_export() {
+        let (rules, manuals) = parse_input::<u32>(EXAMPLE);
+
+        let expected = r#"digraph rules {
+    node [shape=box];
+    "75";
+    "97";
+    "47";
+    "61";
+    "53";
+    "75" -> "47";
+    "97" -> "75" [color=red];
+    "47" -> "61";
+    "61" -> "53";
+}
+"#;
+
+        assert_eq!(rules.to_dot(Some(&manuals[3]), true), expected);
+        assert_eq!(rules.to_dot(None, false).matches(" -> ").count(), 21);
+
+        let (rules, _) = parse_input::<String>("a\"b|c\n\n");
+        assert!(rules.to_dot(None, false).contains(r#""a\"b" -> "c";"#));
+    }
+
+    #[test]
+    fn fix_reports_cycle() {
+        let (rules, manuals) = parse_input::<u32>("1|2\n2|3\n3|1\n3|4\n\n4,3,2,1");
+
+        let cycle = manuals[0].fix(&rules).unwrap_err();
+
+        assert_eq!(cycle.pages, [1, 2, 3]);
+        assert_eq!(cycle.to_string(), "rules form a cycle 1 -> 2 -> 3 -> 1");
     }
 }
@@ -1,7 +1,19 @@
 use std::collections::HashSet;
-use std::fs;
+use std::{env, fs};
+
+#[path = "../repl.rs"]
+mod repl;
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Some("repl") = args.first().map(String::as_str) {
+        let path = args.get(1).map(String::as_str).unwrap_or("Input_Day_6");
+        let input = fs::read_to_string(path).unwrap();
+        repl(GuardSim::new(&input));
+        return;
+    }
+
     let input = fs::read_to_string("Input_Day_6").unwrap();
     let sim = GuardSim::new(&input);
 
@@ -22,6 +34,25 @@ enum Direction {
 }
 
 impl Direction {
+    fn try_new(name: &str) -> Option<Self> {
+        match name {
+            "up" | "^" => Some(Self::Up),
+            "right" | ">" => Some(Self::Right),
+            "down" | "v" => Some(Self::Down),
+            "left" | "<" => Some(Self::Left),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Right => '>',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+        }
+    }
+
     fn turn_right(&self) -> Self {
         match *self {
             Direction::Up => Direction::Right,
@@ -92,6 +123,7 @@ impl From<Guard> for PositionGuard {
     }
 }
 
+#[derive(Clone)]
 struct GuardSim {
     guard: Guard,
     board: Vec<char>,
@@ -193,3 +225,144 @@ impl GuardSim {
         }
     }
 }
+
+fn repl(mut sim: GuardSim) {
+    let mut guard = sim.guard;
+    let mut visited = HashSet::from([PositionGuard::from(guard)]);
+
+    println!(
+        "Loaded {}x{} map, type 'help' for commands",
+        sim.width, sim.height
+    );
+    repl::run_commands(|words| {
+        match words {
+            ["help"] => {
+                println!("show                 print the map, walked cells are marked with X");
+                println!("step [n]             move the guard n steps (default 1)");
+                println!("query <x> <y>        describe a single cell");
+                println!("part1 | part2        solve from the starting guard");
+                println!("set <x|y|dir> <val>  move or turn the starting guard, resets the walk");
+                println!("wall <x> <y>         toggle a wall, resets the walk");
+                println!("reset                put the guard back at the start");
+                println!("quit");
+            }
+            ["show"] => {
+                for y in 0..sim.height {
+                    let row = (0..sim.width)
+                        .map(|x| {
+                            let pos = PositionGuard::new(x, y);
+                            if (guard.x, guard.y) == (x, y) {
+                                guard.dir.to_char()
+                            } else if visited.contains(&pos) {
+                                'X'
+                            } else {
+                                sim.board[pos.get_index(sim.width)]
+                            }
+                        })
+                        .collect::<String>();
+                    println!("{}", row);
+                }
+            }
+            ["step"] | ["step", _] => {
+                let count = match words.get(1).map(|n| n.parse::<usize>()) {
+                    None => 1,
+                    Some(Ok(n)) => n,
+                    Some(Err(_)) => {
+                        println!("Invalid step count: {}", words[1]);
+                        return true;
+                    }
+                };
+
+                for _ in 0..count {
+                    if guard.is_oob(sim.width, sim.height) {
+                        break;
+                    }
+
+                    guard = sim.step(guard);
+
+                    if !guard.is_oob(sim.width, sim.height) {
+                        visited.insert(guard.into());
+                    }
+                }
+
+                if guard.is_oob(sim.width, sim.height) {
+                    println!("Guard left the map, {} cells visited", visited.len());
+                } else {
+                    println!(
+                        "Guard at ({}, {}) facing {}, {} cells visited",
+                        guard.x,
+                        guard.y,
+                        guard.dir.to_char(),
+                        visited.len()
+                    );
+                }
+            }
+            ["query", x, y] => match (x.parse::<i32>(), y.parse::<i32>()) {
+                (Ok(x), Ok(y)) => {
+                    let pos = Guard::new(x, y, Direction::Up);
+                    if pos.is_oob(sim.width, sim.height) {
+                        println!("({}, {}) is off the map", x, y);
+                    } else {
+                        let cell = sim.board[pos.get_index(sim.width)];
+                        let kind = if cell == '#' { "wall" } else { "floor" };
+                        let walked = visited.contains(&PositionGuard::new(x, y));
+                        let occupied = (guard.x, guard.y) == (x, y);
+                        println!(
+                            "({}, {}): {}, visited: {}, guard here: {}",
+                            x, y, kind, walked, occupied
+                        );
+                    }
+                }
+                _ => println!("Invalid position: {} {}", x, y),
+            },
+            ["part1"] => match sim.simulate() {
+                Some(position_set) => println!("Pos Count: {}", position_set.len()),
+                None => println!("Guard is stuck in a loop"),
+            },
+            ["part2"] => match sim.simulate() {
+                Some(position_set) => {
+                    let loop_count = sim.clone().simulate_with_walls(position_set);
+                    println!("Loop Count: {}", loop_count);
+                }
+                None => println!("Guard is stuck in a loop"),
+            },
+            ["set", param, value] => {
+                let start = sim.guard;
+                let new_start = match (*param, value.parse::<i32>(), Direction::try_new(value)) {
+                    ("x", Ok(x), _) => Some(Guard::new(x, start.y, start.dir)),
+                    ("y", Ok(y), _) => Some(Guard::new(start.x, y, start.dir)),
+                    ("dir", _, Some(dir)) => Some(Guard::new(start.x, start.y, dir)),
+                    _ => None,
+                };
+
+                match new_start {
+                    Some(new_start) => {
+                        sim.guard = new_start;
+                        guard = sim.guard;
+                        visited = HashSet::from([PositionGuard::from(guard)]);
+                    }
+                    None => println!("Invalid parameter: {} {}", param, value),
+                }
+            }
+            ["wall", x, y] => match (x.parse::<i32>(), y.parse::<i32>()) {
+                (Ok(x), Ok(y))
+                    if !Guard::new(x, y, Direction::Up).is_oob(sim.width, sim.height) =>
+                {
+                    let index = PositionGuard::new(x, y).get_index(sim.width);
+                    sim.board[index] = if sim.board[index] == '#' { '.' } else { '#' };
+
+                    guard = sim.guard;
+                    visited = HashSet::from([PositionGuard::from(guard)]);
+                }
+                _ => println!("Invalid position: {} {}", x, y),
+            },
+            ["reset"] => {
+                guard = sim.guard;
+                visited = HashSet::from([PositionGuard::from(guard)]);
+            }
+            _ => return false,
+        }
+
+        true
+    });
+}
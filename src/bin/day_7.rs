@@ -1,5 +1,9 @@
 use std::fs;
 
+#[path = "../deadline.rs"]
+mod deadline;
+use deadline::Deadline;
+
 fn main() {
     let input = fs::read_to_string("Input_Day_7").unwrap();
 
@@ -21,9 +25,29 @@ fn main() {
         })
         .collect::<Vec<_>>();
 
-    calibration_inputs
-        .iter_mut()
-        .for_each(|input| input.calibrate());
+    let deadline = Deadline::from_env();
+    for input in calibration_inputs.iter_mut() {
+        input.calibrate(&deadline);
+
+        if deadline.is_expired() {
+            break;
+        }
+    }
+
+    let calibrated_count = calibration_inputs
+        .iter()
+        .filter(|i| i.is_valid().is_some())
+        .count();
+
+    if calibrated_count < calibration_inputs.len() {
+        println!(
+            "Total: timed out after {:?} ({}/{} equations calibrated)",
+            deadline.elapsed(),
+            calibrated_count,
+            calibration_inputs.len()
+        );
+        return;
+    }
 
     let total: u64 = calibration_inputs
         .iter()
@@ -110,12 +134,17 @@ impl CalibrationInput {
         self.is_valid
     }
 
-    pub fn calibrate(&mut self) {
+    /// Tries every operator combination, leaving the result unset if the deadline expires first
+    pub fn calibrate(&mut self, deadline: &Deadline) {
         let mut res = false;
 
         let mut current_op_combo = InputOps::new(self.inputs.len() - 1);
 
         'find_valid_combo: loop {
+            if deadline.is_expired() {
+                return;
+            }
+
             let mut combo_res = self.inputs[0];
 
             for (i, input) in self.inputs[1..].iter().enumerate() {
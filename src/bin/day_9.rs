@@ -1,13 +1,30 @@
 use std::collections::VecDeque;
-use std::fs;
+use std::{env, fs};
+
+#[path = "../repl.rs"]
+mod repl;
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("repl") => {
+            let path = args.get(1).map(String::as_str).unwrap_or("Input_Day_9");
+            let input = fs::read_to_string(path).unwrap();
+            repl(DiskMap::new(&parse_digits(&input)));
+            return;
+        }
+        Some("report") => {
+            let path = args.get(1).map(String::as_str).unwrap_or("Report_Day_9.md");
+            let input = fs::read_to_string("Input_Day_9").unwrap();
+            fs::write(path, report(DiskMap::new(&parse_digits(&input)))).unwrap();
+            return;
+        }
+        _ => (),
+    }
+
     let input = fs::read_to_string("Input_Day_9").unwrap();
-    let disk_map_digits = input
-        .trim()
-        .chars()
-        .map(|c| c.to_digit(10).unwrap() as u8)
-        .collect::<Vec<_>>();
+    let disk_map_digits = parse_digits(&input);
 
     let disk_map = DiskMap::new(&disk_map_digits);
     let mut disk = Disk::new(disk_map.clone());
@@ -20,6 +37,14 @@ fn main() {
     println!("Contiguous Checksum: {}", contiguous_disk.checksum());
 }
 
+fn parse_digits(input: &str) -> Vec<u8> {
+    input
+        .trim()
+        .chars()
+        .map(|c| c.to_digit(10).unwrap() as u8)
+        .collect()
+}
+
 #[derive(Debug, Copy, Clone)]
 enum DiskElement {
     File(usize, u8, u16),
@@ -118,6 +143,20 @@ impl Disk {
         }
     }
 
+    /// Moves the last file block into the first free block before it, returning the
+    /// (from, to) pointers of the move or None if the disk is already compacted
+    pub fn move_last_block(&mut self) -> Option<(usize, usize)> {
+        let from_ptr = self.data.iter().rposition(|block| *block != u16::MAX)?;
+        let to_ptr = self.data[..from_ptr]
+            .iter()
+            .position(|block| *block == u16::MAX)?;
+
+        self.data[to_ptr] = self.data[from_ptr];
+        self.data[from_ptr] = u16::MAX;
+
+        Some((from_ptr, to_ptr))
+    }
+
     pub fn contiguous_fragment(&mut self) {
         for potential_file_index in (0..self.map.elems.len()).rev() {
             if let DiskElement::File(file_ptr, file_len, id) = self.map.elems[potential_file_index]
@@ -163,3 +202,156 @@ impl Disk {
         checksum
     }
 }
+
+/// Builds a markdown report summarising the disk layout after each compaction strategy
+fn report(disk_map: DiskMap) -> String {
+    let mut disk = Disk::new(disk_map.clone());
+    let mut contiguous_disk = Disk::new(disk_map.clone());
+    let original_disk = Disk::new(disk_map);
+
+    disk.fragment();
+    contiguous_disk.contiguous_fragment();
+
+    let layouts = [
+        ("Original", &original_disk),
+        ("Noncontiguous", &disk),
+        ("Contiguous", &contiguous_disk),
+    ];
+
+    let mut out = String::from("# Day 9: Disk Fragmenter\n\n");
+    out.push_str("| Layout | Blocks | Used | Free | Free Runs | Largest Free Run | First Free Block | Checksum |\n");
+    out.push_str("|---|---:|---:|---:|---:|---:|---:|---:|\n");
+
+    for (name, disk) in layouts {
+        let free_runs = disk
+            .data
+            .split(|block| *block != u16::MAX)
+            .filter(|run| !run.is_empty())
+            .map(|run| run.len())
+            .collect::<Vec<_>>();
+        let free_count: usize = free_runs.iter().sum();
+        let first_free = disk
+            .data
+            .iter()
+            .position(|block| *block == u16::MAX)
+            .map(|ptr| ptr.to_string())
+            .unwrap_or_else(|| "-".to_string());
+
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            name,
+            disk.data.len(),
+            disk.data.len() - free_count,
+            free_count,
+            free_runs.len(),
+            free_runs.iter().max().unwrap_or(&0),
+            first_free,
+            disk.checksum()
+        ));
+    }
+
+    for (name, disk) in layouts {
+        let preview = disk
+            .data
+            .iter()
+            .take(80)
+            .map(|block| match *block {
+                u16::MAX => '.',
+                id => char::from_digit(id as u32 % 10, 10).unwrap(),
+            })
+            .collect::<String>();
+
+        out.push_str(&format!(
+            "\n## {}\n\nFirst 80 blocks (file ids mod 10):\n\n```\n{}\n```\n",
+            name, preview
+        ));
+    }
+
+    out
+}
+
+fn repl(disk_map: DiskMap) {
+    let mut disk = Disk::new(disk_map.clone());
+
+    println!(
+        "Loaded disk with {} blocks, type 'help' for commands",
+        disk.data.len()
+    );
+    repl::run_commands(|words| {
+        match words {
+            ["help"] => {
+                println!("show [start] [len]   print blocks, file ids mod 10, free space as '.'");
+                println!("step [n]             move n blocks the way part 1 does (default 1)");
+                println!("query <ptr>          describe a single block");
+                println!("checksum             checksum of the current layout");
+                println!("part1 | part2        solve from the original disk map");
+                println!("reset                restore the original layout");
+                println!("quit");
+            }
+            ["show"] | ["show", _] | ["show", _, _] => {
+                let start = words.get(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+                let len = words.get(2).and_then(|n| n.parse().ok()).unwrap_or(80);
+                let end = (start + len).min(disk.data.len());
+
+                let blocks = disk.data[start.min(end)..end]
+                    .iter()
+                    .map(|block| match *block {
+                        u16::MAX => '.',
+                        id => char::from_digit(id as u32 % 10, 10).unwrap(),
+                    })
+                    .collect::<String>();
+                println!("{}", blocks);
+            }
+            ["step"] | ["step", _] => {
+                let count = match words.get(1).map(|n| n.parse::<usize>()) {
+                    None => 1,
+                    Some(Ok(n)) => n,
+                    Some(Err(_)) => {
+                        println!("Invalid step count: {}", words[1]);
+                        return true;
+                    }
+                };
+
+                let mut last_move = None;
+                for _ in 0..count {
+                    match disk.move_last_block() {
+                        Some(block_move) => last_move = Some(block_move),
+                        None => {
+                            println!("Disk is compacted");
+                            break;
+                        }
+                    }
+                }
+
+                if let Some((from_ptr, to_ptr)) = last_move {
+                    println!(
+                        "Last move: block {} -> {}, checksum {}",
+                        from_ptr,
+                        to_ptr,
+                        disk.checksum()
+                    );
+                }
+            }
+            ["query", ptr] => match ptr.parse::<usize>().ok().and_then(|p| disk.data.get(p)) {
+                Some(&u16::MAX) => println!("Block {}: free", ptr),
+                Some(id) => println!("Block {}: file {}", ptr, id),
+                None => println!("Invalid block: {}", ptr),
+            },
+            ["checksum"] => println!("Checksum: {}", disk.checksum()),
+            ["part1"] => {
+                let mut fresh_disk = Disk::new(disk_map.clone());
+                fresh_disk.fragment();
+                println!("Noncontiguous Checksum: {}", fresh_disk.checksum());
+            }
+            ["part2"] => {
+                let mut fresh_disk = Disk::new(disk_map.clone());
+                fresh_disk.contiguous_fragment();
+                println!("Contiguous Checksum: {}", fresh_disk.checksum());
+            }
+            ["reset"] => disk = Disk::new(disk_map.clone()),
+            _ => return false,
+        }
+
+        true
+    });
+}
@@ -0,0 +1,34 @@
+//! Wall-clock budget for the day binaries with slow parts
+
+use std::env;
+use std::time::{Duration, Instant};
+
+/// Wall-clock budget for a single part, read from `AOC_PART_TIMEOUT_MS` (unbounded when unset)
+#[derive(Debug, Copy, Clone)]
+pub struct Deadline {
+    start: Instant,
+    budget: Option<Duration>,
+}
+
+impl Deadline {
+    pub fn from_env() -> Self {
+        let budget = env::var("AOC_PART_TIMEOUT_MS")
+            .ok()
+            .and_then(|ms| ms.parse::<u64>().ok())
+            .map(Duration::from_millis);
+
+        Self {
+            start: Instant::now(),
+            budget,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.budget
+            .is_some_and(|budget| self.start.elapsed() >= budget)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
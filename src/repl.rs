@@ -0,0 +1,31 @@
+//! Command loop for the days with a repl
+
+use std::io::{self, BufRead, Write};
+
+/// Hands each line of stdin to `handle` split into words, until stdin ends or `quit`/`exit` is
+/// entered. `handle` returns false for a command it doesn't know
+pub fn run_commands(mut handle: impl FnMut(&[&str]) -> bool) {
+    print_prompt();
+
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap();
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words.as_slice() {
+            [] => (),
+            ["quit"] | ["exit"] => break,
+            words => {
+                if !handle(words) {
+                    println!("Unknown command: {}, type 'help' for commands", line.trim());
+                }
+            }
+        }
+
+        print_prompt();
+    }
+}
+
+fn print_prompt() {
+    print!("> ");
+    io::stdout().flush().unwrap();
+}
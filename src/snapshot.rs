@@ -0,0 +1,35 @@
+//! Snapshot assertions for the textual renderings of the day binaries.
+//!
+//! Expected renderings live in `tests/snapshots/<name>.snap`. After an intentional change to a
+//! rendering, rewrite them with `UPDATE_SNAPSHOTS=1 cargo test`.
+
+use std::path::PathBuf;
+use std::{env, fs};
+
+pub fn assert_snapshot(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(format!("{}.snap", name));
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "Missing snapshot {}, run with UPDATE_SNAPSHOTS=1 to create it",
+            path.display()
+        )
+    });
+
+    assert!(
+        expected == actual,
+        "Snapshot {} changed\n--- expected\n{}\n--- actual\n{}\nRun with UPDATE_SNAPSHOTS=1 if this is intentional",
+        path.display(),
+        expected,
+        actual
+    );
+}